@@ -1,7 +1,6 @@
 #![feature(test)]
 extern crate test;
 
-use shell2batch;
 use test::Bencher;
 
 #[bench]
@@ -23,10 +22,10 @@ fn convert(bencher: &mut Bencher) {
             r#"
 
 @REM this is some test code
-copy file1 file2
+copy "file1" "file2"
 
 @REM another
-move file2 file3
+move "file2" "file3"
 "#
         );
     });
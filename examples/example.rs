@@ -1,5 +1,3 @@
-use shell2batch;
-
 fn main() {
     let script = shell2batch::convert(
         r#"
@@ -34,24 +32,24 @@ fn main() {
         r#"
 @echo on
 
-set FILE1=file1
-set FILE2=file2
+set "FILE1=file1"
+set "FILE2=file2"
 
 @REM this is some test code
-copy %FILE1% %FILE2%
-xcopy /E %DIR1% %DIR2%
+copy "%FILE1%" "%FILE2%"
+xcopy /E /I /Y "%DIR1%" "%DIR2%\"
 
 @REM another
-move file2 file3
+move "file2" "file3"
 
-set MY_DIR=directory
+set "MY_DIR=directory"
 
 @REM flags are supported
-rmdir /S /Q %MY_DIR%
+(if exist "%MY_DIR%\*" (rmdir /S /Q "%MY_DIR%") else if exist "%MY_DIR%" (del /F /Q "%MY_DIR%")) 2>nul || cd .
 
 set MY_DIR=
 
-copy /B .\file3+,, .\file3
+(if exist ".\file3" (copy /B ".\file3"+,, ".\file3" >nul) else (type nul >> ".\file3"))
 
 @REM provide custom windows command for specific shell command
 complex_windows_command /flag10 windows_value
@@ -7,102 +7,2613 @@
 #[path = "./converter_test.rs"]
 mod converter_test;
 
-use regex::Regex;
+use crate::escape::{escape, EscapeContext};
+use crate::parser;
+use crate::types::{Conversion, Diagnostic, Options, Severity};
+use std::fmt;
 
 static SHELL2BATCH_PREFIX: &str = "# shell2batch:";
+static EXIT_WITH_ERROR_CODE: &str = "exit /b %ERRORLEVEL%";
+// builtins such as echo do not reset ERRORLEVEL, so set -e checks the result of each command
+// with || (exit /b keeps the exit code of the failed command)
+static ERROR_EXIT: &str = "|| exit /b";
+static SUBSHELL_START: &str = "setlocal & pushd .";
+static SUBSHELL_END: &str = "popd & endlocal";
+// reserved words which start or continue compound commands (if, loops and case)
+static COMPOUND_COMMAND_WORDS: [&str; 13] = [
+    "if", "then", "elif", "else", "fi", "for", "select", "while", "until", "do", "done", "case",
+    "esac",
+];
+// robocopy exit codes below 8 mean success, so they are normalized to 0 (and failures to 1)
+static ROBOCOPY_ERROR_CHECK: &str = "if errorlevel 8 (cmd /c exit 1) else (call )";
 
-fn replace_flags(arguments: &str, flags_mappings: Vec<(&str, &str)>) -> String {
-    let mut windows_arguments = arguments.to_string();
+/// The lines of a here document which are used as the input of the command that started it.
+#[derive(Debug, Default)]
+struct Heredoc {
+    /// The here document lines
+    lines: Vec<String>,
+    /// True if variables are replaced in the lines
+    expand: bool,
+}
+
+/// Holds the script level state which affects the conversion of the following lines.
+#[derive(Debug, Default)]
+struct State {
+    /// The conversion options
+    options: Options,
+    /// True if delayed expansion was enabled by a previous line
+    delayed_expansion: bool,
+    /// True if the script should exit on the first failed command (set -e)
+    errexit: bool,
+    /// True if only the last command of the currently converted and/or list exits on failure (set -e)
+    list_error_check: bool,
+    /// True if the currently converted command is part of an and/or list or a pipeline,
+    /// so its conversion must be a single command which keeps the exit code
+    conditional_command: bool,
+    /// True if the previous directory should be stored on every cd (used by cd -)
+    track_oldpwd: bool,
+    /// The currently open multi line groups, each holding the group type (`(` or `{`)
+    blocks: Vec<char>,
+    /// The line number (starting from 1) of the currently converted line
+    line_number: usize,
+    /// All diagnostics found so far
+    diagnostics: Vec<Diagnostic>,
+    /// The here document of the currently converted line, taken by the command which reads it
+    heredoc: Option<Heredoc>,
+}
+
+impl State {
+    fn add_diagnostic(&mut self, severity: Severity, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            line: self.line_number,
+            severity,
+            message: message.to_string(),
+        });
+    }
+
+    fn escape_context(&self) -> EscapeContext {
+        EscapeContext {
+            delayed_expansion: self.delayed_expansion,
+            in_block: !self.blocks.is_empty(),
+            ..EscapeContext::new()
+        }
+    }
+}
+
+fn convert_var<'a>(value: &'a str, buffer: &mut Vec<&'a str>) {
+    // Batch file vars have one of two forms: `%NAME%` (corresponding to regular variables),
+    // or `%n` if `n` is a digit in the range 0 to 9 or an `*` (corresponding to input params).
+    match value {
+        "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => {
+            buffer.push("%");
+            buffer.push(value);
+        }
+        "@" => buffer.push("%*"),
+        "?" => buffer.push("%ERRORLEVEL%"),
+        _ => {
+            buffer.push("%");
+            buffer.push(value);
+            buffer.push("%");
+        }
+    }
+}
+
+fn replace_full_vars(arguments: &str) -> String {
+    let mut parts: Vec<&str> = arguments.split("${").collect();
+    let mut buffer = vec![];
+
+    buffer.push(parts.remove(0));
+
+    for part in parts {
+        let (before, after, found) = match part.find("}") {
+            None => (part, "", false),
+            Some(index) => {
+                let values = part.split_at(index);
+
+                (values.0, &values.1[1..values.1.len()], true)
+            }
+        };
+
+        if found {
+            convert_var(before, &mut buffer);
+        } else {
+            buffer.push(before)
+        }
+
+        if !after.is_empty() {
+            buffer.push(after);
+        }
+    }
+
+    buffer.join("").to_string()
+}
+
+fn replace_partial_vars(arguments: &str) -> String {
+    let mut parts: Vec<&str> = arguments.split('$').collect();
+    let mut buffer = vec![];
+
+    buffer.push(parts.remove(0));
+
+    for part in parts {
+        // a name is either a single positional parameter or a sequence of word characters
+        let name_length = match part.chars().next() {
+            Some(character)
+                if character.is_ascii_digit() || character == '@' || character == '?' =>
+            {
+                1
+            }
+            _ => part
+                .find(|character: char| !(character.is_ascii_alphanumeric() || character == '_'))
+                .unwrap_or(part.len()),
+        };
+        let (before, after) = part.split_at(name_length);
+
+        if before.is_empty() {
+            buffer.push("$");
+        } else {
+            convert_var(before, &mut buffer);
+        }
+
+        if !after.is_empty() {
+            buffer.push(after);
+        }
+    }
+
+    buffer.join("").to_string()
+}
+
+fn replace_vars(arguments: &str) -> String {
+    let mut updated_arguments = replace_full_vars(arguments);
+    updated_arguments = replace_partial_vars(&updated_arguments);
+
+    updated_arguments
+}
+
+fn escape_char(character: char, context: &EscapeContext) -> String {
+    escape(&character.to_string(), context)
+}
+
+/// Converts shell text (a word or a full arguments string) to the windows batch text.
+///
+/// Variables are replaced only when not single quoted, literal text is escaped
+/// and single quotes are converted to double quotes since cmd does not support them.<br>
+/// Unquoted shell operators such as `&&`, `|` or `>` are left as is since cmd treats them the same way.
+fn convert_text(text: &str, context: &EscapeContext) -> String {
+    convert_shell_text(text, context, true)
+}
+
+/// Converts a shell value to the text the shell would have stored.
+///
+/// The shell quoting is removed and the output is escaped to be placed inside
+/// double quotes, for example: `set "NAME=value"`.
+fn convert_value(text: &str, context: &EscapeContext) -> String {
+    convert_shell_text(text, &context.with_quoted(true), false)
+}
+
+/// Converts a shell value to the text the shell would have stored, escaped based on
+/// the provided context (for example: for unquoted echo text).
+fn convert_literal(text: &str, context: &EscapeContext) -> String {
+    convert_shell_text(text, context, false)
+}
+
+fn convert_shell_text(text: &str, context: &EscapeContext, keep_quotes: bool) -> String {
+    // once the quotes are removed, the quoted text is placed in the same context as the rest
+    let quoted_context = if keep_quotes {
+        context.with_quoted(true)
+    } else {
+        *context
+    };
+    let mut converted = String::with_capacity(text.len());
+    // text which may contain variables, it is escaped but the variables are not yet replaced
+    let mut expandable = String::new();
+    let mut quote: Option<char> = None;
+    // a closing quote is delayed so adjacent quoted strings ('a'"b") are merged into one
+    let mut pending_close_quote = false;
+    let mut characters = text.chars();
+
+    while let Some(character) = characters.next() {
+        if pending_close_quote {
+            pending_close_quote = false;
+
+            if quote.is_none() && (character == '\'' || character == '"') {
+                quote = Some(character);
+                continue;
+            }
+
+            converted.push('"');
+        }
+
+        match quote {
+            Some(quote_char) if character == quote_char => {
+                converted.push_str(&replace_vars(&expandable));
+                expandable.clear();
+                quote = None;
+                pending_close_quote = keep_quotes;
+            }
+            Some('\'') => match character {
+                '"' if keep_quotes => converted.push_str("\\\""),
+                _ => converted.push_str(&escape_char(character, &quoted_context)),
+            },
+            Some(_) => match character {
+                '\\' => match characters.next() {
+                    Some(next_character)
+                        if next_character == '$'
+                            || (!keep_quotes && "\"\\`".contains(next_character)) =>
+                    {
+                        converted.push_str(&replace_vars(&expandable));
+                        expandable.clear();
+                        converted.push(next_character);
+                    }
+                    Some(next_character) => {
+                        expandable.push(character);
+                        expandable.push_str(&escape_char(next_character, &quoted_context));
+                    }
+                    None => expandable.push(character),
+                },
+                _ => expandable.push_str(&escape_char(character, &quoted_context)),
+            },
+            None => match character {
+                '\'' | '"' => {
+                    converted.push_str(&replace_vars(&expandable));
+                    expandable.clear();
+                    quote = Some(character);
+                    if keep_quotes {
+                        converted.push('"');
+                    }
+                }
+                '\\' => match characters.next() {
+                    Some(next_character) if "%^&|<>()!".contains(next_character) => {
+                        expandable.push_str(&escape_char(next_character, context))
+                    }
+                    Some(next_character)
+                        if next_character == '$' || next_character == '\'' || !keep_quotes =>
+                    {
+                        converted.push_str(&replace_vars(&expandable));
+                        expandable.clear();
+                        converted.push(next_character);
+                    }
+                    Some(next_character) => {
+                        expandable.push(character);
+                        expandable.push(next_character);
+                    }
+                    None => expandable.push(character),
+                },
+                '%' | '!' | '^' => expandable.push_str(&escape_char(character, context)),
+                _ => expandable.push(character),
+            },
+        }
+    }
+
+    converted.push_str(&replace_vars(&expandable));
+    if pending_close_quote {
+        converted.push('"');
+    }
+
+    converted
+}
+
+/// Converts a shell path to a windows path value (without the surrounding quotes).
+fn convert_path_value(word: &str, state: &State) -> String {
+    let (prefix, path_word) = if word == "~" || word.starts_with("~/") {
+        ("%USERPROFILE%", &word[1..])
+    } else {
+        ("", word)
+    };
+
+    let mut path = convert_value(path_word, &state.escape_context());
+
+    // msys style drive paths, for example: /c/work
+    let characters: Vec<char> = path.chars().take(3).collect();
+    if prefix.is_empty()
+        && characters.len() >= 2
+        && characters[0] == '/'
+        && characters[1].is_ascii_alphabetic()
+        && (characters.len() == 2 || characters[2] == '/')
+    {
+        let drive_path = if characters.len() == 2 {
+            "/"
+        } else {
+            &path[2..]
+        };
+        path = format!("{}:{}", characters[1], drive_path);
+    }
+
+    format!("{}{}", prefix, path.replace('/', "\\"))
+}
+
+/// Converts a shell path to a double quoted windows path.
+fn quote_path(word: &str, state: &State) -> String {
+    format!("\"{}\"", convert_path_value(word, state))
+}
+
+/// Separates the redirections from the other arguments and converts them.
+fn split_redirections(arguments: &[String], state: &mut State) -> (Vec<String>, Vec<String>) {
+    let mut operands = vec![];
+    let mut redirections = vec![];
+    let mut words = arguments.iter();
+
+    while let Some(word) = words.next() {
+        let (operator, target) = match parser::split_redirection(word) {
+            Some((operator, "")) => match words.next() {
+                Some(target) => (operator, target.as_str()),
+                None => (operator, ""),
+            },
+            Some(redirection) => redirection,
+            None => {
+                operands.push(word.to_string());
+                continue;
+            }
+        };
+
+        // here documents are collected by run and here strings are not supported
+        if operator.starts_with("<<") {
+            if operator == "<<<" {
+                state.add_diagnostic(
+                    Severity::Warning,
+                    "here strings are not supported and are ignored",
+                );
+            }
+            continue;
+        }
+
+        let windows_target = if target == "/dev/null" {
+            "nul".to_string()
+        } else if operator.ends_with('&') {
+            target.to_string()
+        } else {
+            quote_path(target, state)
+        };
+
+        let redirection = match operator.strip_prefix('&') {
+            Some(output_operator) => format!("{}{} 2>&1", output_operator, windows_target),
+            None => format!("{}{}", operator, windows_target),
+        };
+        redirections.push(redirection);
+    }
+
+    (operands, redirections)
+}
+
+fn append_redirections(windows_command: String, redirections: &[String]) -> String {
+    if redirections.is_empty() {
+        windows_command
+    } else {
+        format!("{} {}", windows_command, redirections.join(" "))
+    }
+}
+
+/// Adds the redirections to the commands created by print_lines.
+fn append_print_redirections(windows_command: String, redirections: &[String]) -> String {
+    // echo writes the space before a following redirection, so a single echo is redirected first
+    if redirections.is_empty() || windows_command.starts_with('(') {
+        append_redirections(windows_command, redirections)
+    } else {
+        format!("{} {}", redirections.join(" "), windows_command)
+    }
+}
+
+fn convert_cd(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+    // -L and -P have no meaning on windows
+    let operand = operands
+        .iter()
+        .find(|operand| operand.len() == 1 || !operand.starts_with('-'));
+    let previous_directory = operand.is_some_and(|operand| operand == "-");
+    let directory = match operand {
+        None => "%USERPROFILE%".to_string(),
+        Some(_) if previous_directory => "%OLDPWD%".to_string(),
+        Some(operand) => convert_path_value(operand, state),
+    };
+
+    // cd /d also changes the current drive
+    let mut windows_command = format!("cd /d \"{}\"", directory);
+    if state.track_oldpwd && state.conditional_command {
+        // %CD% and %OLDPWD% are expanded when the whole line is read, so call expands
+        // them again when the command runs after the previous commands of the list
+        windows_command = if previous_directory {
+            "(call set \"SHELL2BATCH_OLDPWD=%%OLDPWD%%\" & call set \"OLDPWD=%%CD%%\" & call cd /d \"%%SHELL2BATCH_OLDPWD%%\")".to_string()
+        } else {
+            format!("(call set \"OLDPWD=%%CD%%\" & {})", windows_command)
+        };
+    } else if state.track_oldpwd {
+        windows_command = format!("set \"OLDPWD=%CD%\" & {}", windows_command);
+    }
+
+    append_redirections(windows_command, &redirections)
+}
+
+fn convert_pushd(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let windows_command = match operands.first() {
+        Some(operand) => format!("pushd {}", quote_path(operand, state)),
+        None => {
+            state.add_diagnostic(
+                Severity::Warning,
+                "pushd without a directory is not supported",
+            );
+            "pushd".to_string()
+        }
+    };
+
+    append_redirections(windows_command, &redirections)
+}
+
+/// Expands the echo -e escape sequences and returns the output lines and
+/// whether a new line should be printed after the last line.
+fn expand_echo_escapes(text: &str) -> (Vec<String>, bool) {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut characters = text.chars();
+
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            line.push(character);
+            continue;
+        }
+
+        match characters.next() {
+            Some('n') => lines.push(std::mem::take(&mut line)),
+            Some('t') => line.push('\t'),
+            Some('\\') => line.push('\\'),
+            // \c stops the output, including the last new line
+            Some('c') => {
+                lines.push(line);
+                return (lines, false);
+            }
+            Some(next_character) => {
+                line.push(character);
+                line.push(next_character);
+            }
+            None => line.push(character),
+        }
+    }
+
+    lines.push(line);
+    (lines, true)
+}
+
+/// Returns the echo output lines (escaped based on the provided context) and
+/// whether a new line should be printed after the last line.
+fn echo_lines(
+    operands: &[String],
+    interpret_escapes: bool,
+    context: &EscapeContext,
+) -> (Vec<String>, bool) {
+    let text = operands
+        .iter()
+        .map(|operand| convert_literal(operand, context))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    if interpret_escapes {
+        expand_echo_escapes(&text)
+    } else {
+        (vec![text], true)
+    }
+}
+
+fn convert_echo(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut new_line = true;
+    let mut interpret_escapes = false;
+    let mut text_index = 0;
+    for operand in &operands {
+        let is_option = operand.len() > 1
+            && operand.starts_with('-')
+            && operand[1..].chars().all(|option| "neE".contains(option));
+        if !is_option {
+            break;
+        }
+
+        for option in operand[1..].chars() {
+            match option {
+                'n' => new_line = false,
+                'e' => interpret_escapes = true,
+                _ => interpret_escapes = false,
+            }
+        }
+        text_index += 1;
+    }
+
+    let text_operands = &operands[text_index..];
+    let windows_command = print_lines(
+        |context| {
+            let (lines, escapes_new_line) = echo_lines(text_operands, interpret_escapes, context);
+            (lines, new_line && escapes_new_line)
+        },
+        state,
+    );
+
+    append_print_redirections(windows_command, &redirections)
+}
+
+/// Creates the commands which print the provided lines.
+///
+/// The lines and whether a new line is printed after the last line are created by the provided
+/// function for the given escape context, since the last line may be printed inside quotes.
+fn print_lines<F>(create_lines: F, state: &State) -> String
+where
+    F: Fn(&EscapeContext) -> (Vec<String>, bool),
+{
+    let mut escape_context = state.escape_context();
+    let (mut lines, mut new_line) = create_lines(&escape_context);
+
+    // multiple commands are grouped in a block, so the lines are escaped for the block
+    if (lines.len() > 1 || !new_line) && !escape_context.in_block {
+        escape_context.in_block = true;
+        (lines, new_line) = create_lines(&escape_context);
+    }
+
+    let mut windows_commands = vec![];
+    let last_line = lines.pop().unwrap_or_default();
+    for line in lines {
+        windows_commands.push(echo_line(&line));
+    }
+
+    if new_line {
+        windows_commands.push(echo_line(&last_line));
+    } else {
+        // set /p prints text without a new line, the text is placed in quotes so it is escaped again
+        let (mut quoted_lines, _) = create_lines(&escape_context.with_quoted(true));
+        let quoted_last_line = quoted_lines.pop().unwrap_or_default();
+
+        // set /p fails when there is no input, so (call ) is used to reset the exit code
+        windows_commands.push(format!("<nul set /p =\"{}\" & (call )", quoted_last_line));
+    }
+
+    if windows_commands.len() == 1 && new_line {
+        windows_commands.remove(0)
+    } else {
+        format!("({})", windows_commands.join("& "))
+    }
+}
+
+// placeholders for the printf arguments and literal percent signs which are not modified by the conversion
+const PRINTF_ARGUMENT: char = '\u{E000}';
+const PRINTF_PERCENT: char = '\u{E001}';
+// placeholder for the %b arguments, which are printed with their backslash escapes expanded
+const PRINTF_ESCAPED_ARGUMENT: char = '\u{E002}';
+
+/// Replaces the printf format directives with placeholders.
+fn parse_printf_format(format: &str, state: &mut State) -> String {
+    let mut parsed = String::with_capacity(format.len());
+    let mut characters = format.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        if character != '%' {
+            parsed.push(character);
+            continue;
+        }
+
+        if characters.peek() == Some(&'%') {
+            characters.next();
+            parsed.push(PRINTF_PERCENT);
+            continue;
+        }
+
+        let mut modifiers = String::new();
+        while let Some(modifier) = characters.next_if(|next| "-+ #0123456789.*".contains(*next)) {
+            modifiers.push(modifier);
+        }
+
+        match characters.next() {
+            Some(conversion) if conversion.is_ascii_alphabetic() => {
+                if !modifiers.is_empty() {
+                    state.add_diagnostic(
+                        Severity::Warning,
+                        &format!(
+                            "printf flags, width and precision in %{}{} are not supported and are ignored",
+                            modifiers, conversion
+                        ),
+                    );
+                }
+                if !"sdib".contains(conversion) {
+                    state.add_diagnostic(
+                        Severity::Warning,
+                        &format!(
+                            "printf conversion %{} is not supported and the argument is printed as is",
+                            conversion
+                        ),
+                    );
+                }
+
+                if conversion == 'b' {
+                    parsed.push(PRINTF_ESCAPED_ARGUMENT);
+                } else {
+                    parsed.push(PRINTF_ARGUMENT);
+                }
+            }
+            // not a directive, printed as is
+            Some(other_character) => {
+                parsed.push(PRINTF_PERCENT);
+                parsed.push_str(&modifiers);
+                parsed.push(other_character);
+            }
+            None => {
+                parsed.push(PRINTF_PERCENT);
+                parsed.push_str(&modifiers);
+            }
+        }
+    }
+
+    parsed
+}
+
+/// Returns the printf output lines (escaped based on the provided context) and
+/// whether the output ends with a new line.
+fn printf_lines(
+    format: &str,
+    arguments: &[String],
+    context: &EscapeContext,
+) -> (Vec<String>, bool) {
+    // only the format and the %b arguments are expanded, the other arguments are printed as is
+    let (format_lines, format_completed) = expand_echo_escapes(&convert_literal(format, context));
+    let expanded_format = format_lines.join("\n");
+    let converted_arguments: Vec<String> = arguments
+        .iter()
+        .map(|argument| convert_literal(argument, context))
+        .collect();
+    let percent = escape("%", context);
+
+    // the format is reused as long as there are arguments left
+    let mut text = String::new();
+    let mut completed = format_completed;
+    let mut argument_index = 0;
+    'format: loop {
+        for character in expanded_format.chars() {
+            match character {
+                PRINTF_ARGUMENT => {
+                    if let Some(argument) = converted_arguments.get(argument_index) {
+                        text.push_str(argument);
+                    }
+                    argument_index += 1;
+                }
+                PRINTF_ESCAPED_ARGUMENT => {
+                    if let Some(argument) = converted_arguments.get(argument_index) {
+                        let (argument_lines, argument_completed) = expand_echo_escapes(argument);
+                        text.push_str(&argument_lines.join("\n"));
+
+                        // \c in a %b argument stops the output
+                        if !argument_completed {
+                            completed = false;
+                            break 'format;
+                        }
+                    }
+                    argument_index += 1;
+                }
+                PRINTF_PERCENT => text.push_str(&percent),
+                _ => text.push(character),
+            }
+        }
+
+        if !completed || argument_index == 0 || argument_index >= converted_arguments.len() {
+            break;
+        }
+    }
+
+    let mut lines: Vec<String> = text.split('\n').map(|line| line.to_string()).collect();
+    let new_line = completed && lines.len() > 1 && lines.last().unwrap().is_empty();
+    if new_line {
+        lines.pop();
+    }
+
+    (lines, new_line)
+}
+
+fn convert_printf(arguments: &[String], state: &mut State) -> String {
+    let (mut operands, redirections) = split_redirections(arguments, state);
+
+    if operands
+        .first()
+        .map(|operand| operand == "--")
+        .unwrap_or(false)
+    {
+        operands.remove(0);
+    }
+
+    let windows_command = match operands.split_first() {
+        None => {
+            state.add_diagnostic(Severity::Warning, "printf without a format is ignored");
+            "".to_string()
+        }
+        Some((format, _)) if format.starts_with('-') => {
+            state.add_diagnostic(
+                Severity::Warning,
+                &format!("printf option {} is not supported", format),
+            );
+            "".to_string()
+        }
+        Some((format, format_arguments)) => {
+            let parsed_format = parse_printf_format(format, state);
+
+            print_lines(
+                |context| printf_lines(&parsed_format, format_arguments, context),
+                state,
+            )
+        }
+    };
+
+    append_print_redirections(windows_command, &redirections)
+}
+
+fn echo_line(line: &str) -> String {
+    // echo without text prints the echo mode and echo on/off changes it
+    let lowercase_line = line.to_lowercase();
+    if line.is_empty()
+        || lowercase_line == "on"
+        || lowercase_line == "off"
+        || line.starts_with("/?")
+    {
+        format!("echo.{}", line)
+    } else {
+        format!("echo {}", line)
+    }
+}
+
+/// Converts a here document line to the echo text, variables are replaced only if enabled.
+fn convert_heredoc_line(line: &str, expand: bool, context: &EscapeContext) -> String {
+    if !expand {
+        return escape(line, context);
+    }
+
+    let mut converted = String::with_capacity(line.len());
+    let mut expandable = String::new();
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match characters.peek() {
+            Some(next_character) if character == '\\' && "$`\\".contains(*next_character) => {
+                converted.push_str(&replace_vars(&expandable));
+                expandable.clear();
+                converted.push_str(&escape_char(*next_character, context));
+                characters.next();
+            }
+            _ => expandable.push_str(&escape_char(character, context)),
+        }
+    }
+    converted.push_str(&replace_vars(&expandable));
+
+    converted
+}
+
+/// A command option, for example: `-r`, `-t dir` or `--target-directory=dir`.
+#[derive(Debug, PartialEq, Eq)]
+struct CommandOption {
+    /// The option name without the leading hyphens
+    name: String,
+    /// The option value, provided for the options which take a value and for `--name=value`
+    value: Option<String>,
+}
+
+impl fmt::Display for CommandOption {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match &self.value {
+            Some(value) if self.name.len() > 1 => write!(formatter, "--{}={}", self.name, value),
+            _ if self.name.len() > 1 => write!(formatter, "--{}", self.name),
+            _ => write!(formatter, "-{}", self.name),
+        }
+    }
+}
+
+/// Separates the options from the other operands, all the words after `--` are operands.
+///
+/// Short options may be combined in a single word (for example: `-rf`).<br>
+/// The options listed in value_options take a value, which is the rest of the word or the next word.
+fn parse_options(
+    operands: Vec<String>,
+    value_options: &[&str],
+) -> (Vec<CommandOption>, Vec<String>) {
+    let mut options = vec![];
+    let mut other_operands = vec![];
+    let mut operands = operands.into_iter();
+
+    while let Some(operand) = operands.next() {
+        if operand == "-" || !operand.starts_with('-') {
+            other_operands.push(operand);
+            continue;
+        }
+
+        match operand.strip_prefix("--") {
+            Some("") => {
+                other_operands.extend(operands.by_ref());
+            }
+            Some(long_option) => {
+                let option = match long_option.split_once('=') {
+                    Some((name, value)) => CommandOption {
+                        name: name.to_string(),
+                        value: Some(value.to_string()),
+                    },
+                    None => CommandOption {
+                        name: long_option.to_string(),
+                        value: if value_options.contains(&long_option) {
+                            operands.next()
+                        } else {
+                            None
+                        },
+                    },
+                };
+                options.push(option);
+            }
+            None => {
+                for (index, flag) in operand.char_indices().skip(1) {
+                    let name = flag.to_string();
+                    if !value_options.contains(&name.as_str()) {
+                        options.push(CommandOption { name, value: None });
+                        continue;
+                    }
+
+                    let rest = &operand[index + flag.len_utf8()..];
+                    let value = if rest.is_empty() {
+                        operands.next()
+                    } else {
+                        Some(rest.to_string())
+                    };
+                    options.push(CommandOption { name, value });
+                    break;
+                }
+            }
+        }
+    }
+
+    (options, other_operands)
+}
+
+fn unsupported_option(command: &str, option: &CommandOption, state: &mut State) {
+    state.add_diagnostic(
+        Severity::Warning,
+        &format!(
+            "{} option {} is not supported and is ignored",
+            command, option
+        ),
+    );
+}
+
+/// Reports the options which are not supported by the command and returns the other operands.
+fn ignore_options(command: &str, operands: Vec<String>, state: &mut State) -> Vec<String> {
+    let (options, files) = parse_options(operands, &[]);
+    for option in &options {
+        unsupported_option(command, option, state);
+    }
+
+    files
+}
+
+fn convert_cat(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+    let files = ignore_options("cat", operands, state);
+
+    let windows_command = if files.is_empty() {
+        match state.heredoc.take() {
+            Some(heredoc) if heredoc.lines.is_empty() => "type nul".to_string(),
+            Some(heredoc) => {
+                let windows_command = print_lines(
+                    |context| {
+                        let lines = heredoc
+                            .lines
+                            .iter()
+                            .map(|line| convert_heredoc_line(line, heredoc.expand, context))
+                            .collect();
+                        (lines, true)
+                    },
+                    state,
+                );
+
+                return append_print_redirections(windows_command, &redirections);
+            }
+            // findstr prints all the input lines
+            None => "findstr \"^\"".to_string(),
+        }
+    } else if files.len() == 1 && files[0] == "-" {
+        "findstr \"^\"".to_string()
+    } else {
+        let paths: Vec<String> = files.iter().map(|file| quote_path(file, state)).collect();
+        let windows_command = format!("type {}", paths.join(" "));
+
+        // type prints the name of each file to the error output when given multiple files
+        if paths.len() > 1 {
+            format!("{} 2>nul", windows_command)
+        } else {
+            windows_command
+        }
+    };
+
+    append_redirections(windows_command, &redirections)
+}
+
+/// Parses the head and tail options and returns the line count (as written, for example: `+5`)
+/// and the file operands.
+fn parse_line_count(
+    command: &str,
+    operands: Vec<String>,
+    state: &mut State,
+) -> (Option<String>, Vec<String>) {
+    let options_end = operands
+        .iter()
+        .position(|operand| operand == "--")
+        .unwrap_or(operands.len());
+    let operands = operands
+        .into_iter()
+        .enumerate()
+        .map(|(index, operand)| {
+            // obsolete syntax, for example: head -5
+            let obsolete_count = index < options_end
+                && operand.len() > 1
+                && operand.starts_with('-')
+                && operand[1..]
+                    .chars()
+                    .all(|character| character.is_ascii_digit());
+            if obsolete_count {
+                format!("-n{}", &operand[1..])
+            } else {
+                operand
+            }
+        })
+        .collect();
+
+    let mut count = None;
+    let (options, files) = parse_options(operands, &["n", "c", "lines", "bytes"]);
+    for option in options {
+        match option.name.as_str() {
+            "n" | "lines" => count = option.value,
+            "c" | "bytes" => state.add_diagnostic(
+                Severity::Warning,
+                &format!("{} byte counts are not supported and are ignored", command),
+            ),
+            _ => unsupported_option(command, &option, state),
+        }
+    }
+
+    (count, files)
+}
+
+/// Returns the line count as a number, if the count is not a plain number the default is used.
+fn line_count_value(command: &str, count: &str, state: &mut State) -> usize {
+    match count.parse::<usize>() {
+        Ok(value) => value,
+        Err(_) => {
+            state.add_diagnostic(
+                Severity::Warning,
+                &format!(
+                    "{} line count {} is not supported, the default of 10 lines is used",
+                    command, count
+                ),
+            );
+            10
+        }
+    }
+}
+
+fn convert_head(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+    let (count, files) = parse_line_count("head", operands, state);
+    let count = line_count_value("head", &count.unwrap_or_else(|| "10".to_string()), state);
+
+    // findstr numbers the lines (and reads the input when no file is given)
+    let sources = if files.is_empty() {
+        vec!["findstr /n \"^\"".to_string()]
+    } else {
+        files
+            .iter()
+            .map(|file| format!("findstr /n \"^\" {}", quote_path(file, state)))
+            .collect()
+    };
+
+    let windows_commands: Vec<String> = sources
+        .iter()
+        .map(|source| {
+            format!(
+                "for /f \"tokens=1* delims=:\" %%a in ('{}') do @if %%a leq {} echo(%%b",
+                source, count
+            )
+        })
+        .collect();
+
+    // the loops are grouped so the redirections and following commands are not part of the loop body
+    append_redirections(format!("({})", windows_commands.join(" & ")), &redirections)
+}
+
+fn convert_tail(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+    let (count, files) = parse_line_count("tail", operands, state);
+    let count = count.unwrap_or_else(|| "10".to_string());
+
+    let paths: Vec<String> = files.iter().map(|file| quote_path(file, state)).collect();
+    let windows_commands: Vec<String> = match count.strip_prefix('+') {
+        // tail -n +K prints from line K, more +N skips the first N lines
+        Some(start) => {
+            let skip = line_count_value("tail", start, state).saturating_sub(1);
+            let more_command = if skip > 0 {
+                format!("more +{}", skip)
+            } else {
+                "more".to_string()
+            };
+
+            if paths.is_empty() {
+                vec![more_command]
+            } else {
+                paths
+                    .iter()
+                    .map(|path| format!("{} {}", more_command, path))
+                    .collect()
+            }
+        }
+        None => {
+            let count = line_count_value("tail", &count, state);
+
+            if paths.is_empty() {
+                state.add_diagnostic(
+                    Severity::Warning,
+                    "tail -n without a file is not supported and prints the whole input",
+                );
+                vec!["more".to_string()]
+            } else {
+                // the lines are counted first, call expands the skip count after it is set
+                paths
+                    .iter()
+                    .map(|path| {
+                        format!(
+                            "(for /f %%c in ('find /c /v \"\" ^< {path}') do @if %%c gtr {count} (set /a \"SHELL2BATCH_SKIP=%%c-{count}\" >nul & call more +%%SHELL2BATCH_SKIP%% {path}) else type {path})",
+                            path = path,
+                            count = count
+                        )
+                    })
+                    .collect()
+            }
+        }
+    };
+
+    let windows_command = if windows_commands.len() > 1 {
+        format!("({})", windows_commands.join(" & "))
+    } else {
+        windows_commands.join("")
+    };
+
+    append_redirections(windows_command, &redirections)
+}
+
+fn convert_wc(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut count_lines = false;
+    let (options, files) = parse_options(operands, &[]);
+    for option in &options {
+        match option.name.as_str() {
+            "l" | "lines" => count_lines = true,
+            _ => state.add_diagnostic(
+                Severity::Warning,
+                &format!(
+                    "wc option {} is not supported and is ignored, only lines are counted",
+                    option
+                ),
+            ),
+        }
+    }
+
+    if !count_lines {
+        state.add_diagnostic(
+            Severity::Warning,
+            "wc without -l is not supported, only lines are counted",
+        );
+    }
+
+    // find prints only the count when reading the input and the file names when given multiple files
+    let windows_command = match files.len() {
+        0 => "find /c /v \"\"".to_string(),
+        1 if files[0] == "-" => "find /c /v \"\"".to_string(),
+        1 => format!("find /c /v \"\" < {}", quote_path(&files[0], state)),
+        _ => {
+            state.add_diagnostic(
+                Severity::Warning,
+                "wc with multiple files prints the find output format",
+            );
+            let paths: Vec<String> = files.iter().map(|file| quote_path(file, state)).collect();
+            format!("find /c /v \"\" {}", paths.join(" "))
+        }
+    };
+
+    append_redirections(windows_command, &redirections)
+}
+
+/// Returns the findstr character class for a POSIX character class name (for example: `digit`).
+fn posix_class(name: &str) -> Option<&'static str> {
+    match name {
+        "digit" => Some("0-9"),
+        "alpha" => Some("a-zA-Z"),
+        "alnum" => Some("a-zA-Z0-9"),
+        "upper" => Some("A-Z"),
+        "lower" => Some("a-z"),
+        "xdigit" => Some("0-9a-fA-F"),
+        "space" | "blank" => Some(" \t"),
+        _ => None,
+    }
+}
+
+/// Converts a grep regular expression (basic or extended) to the findstr dialect.
+///
+/// findstr only supports `.`, `*`, `^`, `$`, character classes and word positions (`\<` and `\>`).<br>
+/// `+` is rewritten as the repeated atom followed by `*`, other constructs are reported.
+fn convert_grep_regex(pattern: &str, extended: bool, state: &mut State) -> String {
+    let characters: Vec<char> = pattern.chars().collect();
+    let mut converted = String::with_capacity(pattern.len());
+    let mut last_atom = String::new();
+    let mut unsupported = vec![];
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        index += 1;
+
+        let atom = match character {
+            '\\' if index < characters.len() => {
+                let next_character = characters[index];
+                index += 1;
+
+                match next_character {
+                    'd' => "[0-9]".to_string(),
+                    'D' => "[^0-9]".to_string(),
+                    'w' => "[a-zA-Z0-9_]".to_string(),
+                    'W' => "[^a-zA-Z0-9_]".to_string(),
+                    's' => "[ \t]".to_string(),
+                    'S' => "[^ \t]".to_string(),
+                    '<' | '>' => {
+                        converted.push(character);
+                        converted.push(next_character);
+                        last_atom.clear();
+                        continue;
+                    }
+                    // basic regular expression operators (a GNU extension)
+                    '+' | '?' | '|' | '{' | '}' | '(' | ')' if !extended => {
+                        unsupported.push(format!("\\{}", next_character));
+                        continue;
+                    }
+                    '+' | '?' | '|' | '{' | '}' | '(' | ')' => next_character.to_string(),
+                    _ if next_character.is_ascii_alphanumeric() => {
+                        unsupported.push(format!("\\{}", next_character));
+                        continue;
+                    }
+                    _ => format!("\\{}", next_character),
+                }
+            }
+            '[' => {
+                let mut class = String::from("[");
+                if characters.get(index) == Some(&'^') {
+                    class.push('^');
+                    index += 1;
+                }
+                // a closing bracket right after the opening one is part of the class
+                if characters.get(index) == Some(&']') {
+                    class.push(']');
+                    index += 1;
+                }
+
+                while index < characters.len() && characters[index] != ']' {
+                    let class_text: String = characters[index..].iter().collect();
+                    let posix_name = class_text
+                        .strip_prefix("[:")
+                        .and_then(|rest| rest.find(":]").map(|end| rest[..end].to_string()));
+
+                    match posix_name {
+                        Some(name) => {
+                            match posix_class(&name) {
+                                Some(findstr_class) => class.push_str(findstr_class),
+                                None => unsupported.push(format!("[:{}:]", name)),
+                            }
+                            index += name.chars().count() + 4;
+                        }
+                        None => {
+                            class.push(characters[index]);
+                            index += 1;
+                        }
+                    }
+                }
+                class.push(']');
+                index += 1;
+
+                class
+            }
+            '+' if extended && !last_atom.is_empty() => {
+                converted.push_str(&last_atom);
+                converted.push('*');
+                last_atom.clear();
+                continue;
+            }
+            '?' | '|' | '{' | '}' | '(' | ')' if extended => {
+                unsupported.push(character.to_string());
+                continue;
+            }
+            '*' | '^' | '$' => {
+                converted.push(character);
+                last_atom.clear();
+                continue;
+            }
+            _ => character.to_string(),
+        };
+
+        converted.push_str(&atom);
+        last_atom = atom;
+    }
+
+    for construct in unsupported {
+        state.add_diagnostic(
+            Severity::Warning,
+            &format!(
+                "grep regular expression {} is not supported by findstr and is ignored",
+                construct
+            ),
+        );
+    }
+
+    converted
+}
+
+/// Splits an extended regular expression on the top level `|` alternatives.
+fn split_alternatives(pattern: &str) -> Vec<String> {
+    let mut alternatives = vec![];
+    let mut alternative = String::new();
+    let mut depth = 0;
+    let mut in_class = false;
+    let mut characters = pattern.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => {
+                alternative.push(character);
+                if let Some(next_character) = characters.next() {
+                    alternative.push(next_character);
+                }
+                continue;
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '(' if !in_class => depth += 1,
+            ')' if !in_class && depth > 0 => depth -= 1,
+            '|' if !in_class && depth == 0 => {
+                alternatives.push(std::mem::take(&mut alternative));
+                continue;
+            }
+            _ => (),
+        }
+        alternative.push(character);
+    }
+    alternatives.push(alternative);
+
+    alternatives
+}
+
+fn is_literal_pattern(pattern: &str, extended: bool) -> bool {
+    let special_characters = if extended {
+        ".[]*^$\\+?|{}()"
+    } else {
+        ".[]*^$\\"
+    };
+
+    !pattern
+        .chars()
+        .any(|character| special_characters.contains(character))
+}
+
+/// Holds the parsed grep options.
+#[derive(Debug, Default)]
+struct GrepOptions {
+    /// The search patterns (from -e or the first operand)
+    patterns: Vec<String>,
+    /// The files (or directories when recursive) to search
+    files: Vec<String>,
+    /// -i
+    ignore_case: bool,
+    /// -v
+    invert: bool,
+    /// -r or -R
+    recursive: bool,
+    /// -n
+    line_number: bool,
+    /// -l
+    files_with_matches: bool,
+    /// -q
+    quiet: bool,
+    /// -s
+    no_messages: bool,
+    /// -c
+    count: bool,
+    /// -x
+    line_regexp: bool,
+    /// -w
+    word_regexp: bool,
+    /// -E
+    extended: bool,
+    /// -F
+    fixed: bool,
+}
+
+fn parse_grep_options(operands: Vec<String>, state: &mut State) -> GrepOptions {
+    let mut options = GrepOptions::default();
+    let (grep_options, operands_left) = parse_options(
+        operands,
+        &[
+            "e",
+            "A",
+            "B",
+            "C",
+            "m",
+            "regexp",
+            "after-context",
+            "before-context",
+            "context",
+            "max-count",
+        ],
+    );
+
+    for option in grep_options {
+        match option.name.as_str() {
+            "i" | "ignore-case" => options.ignore_case = true,
+            "v" | "invert-match" => options.invert = true,
+            "r" | "R" | "recursive" | "dereference-recursive" => options.recursive = true,
+            "n" | "line-number" => options.line_number = true,
+            "l" | "files-with-matches" => options.files_with_matches = true,
+            "q" | "quiet" | "silent" => options.quiet = true,
+            "s" | "no-messages" => options.no_messages = true,
+            "c" | "count" => options.count = true,
+            "x" | "line-regexp" => options.line_regexp = true,
+            "w" | "word-regexp" => options.word_regexp = true,
+            "E" | "extended-regexp" => options.extended = true,
+            "F" | "fixed-strings" => options.fixed = true,
+            "G" | "basic-regexp" => options.extended = false,
+            "e" | "regexp" => options.patterns.extend(option.value),
+            _ => unsupported_option("grep", &option, state),
+        }
+    }
+
+    // without -e the first operand is the pattern
+    let mut operands_left = operands_left.into_iter();
+    if options.patterns.is_empty() {
+        if let Some(pattern) = operands_left.next() {
+            options.patterns.push(pattern);
+        }
+    }
+    options.files = operands_left.collect();
+
+    options
+}
+
+/// Converts grep to find (for literal patterns) or findstr.
+fn convert_grep(arguments: &[String], state: &mut State) -> String {
+    let (operands, mut redirections) = split_redirections(arguments, state);
+    let options = parse_grep_options(operands, state);
+
+    if options.patterns.is_empty() {
+        state.add_diagnostic(Severity::Warning, "grep without a pattern is not supported");
+        return append_redirections("findstr".to_string(), &redirections);
+    }
+
+    let escape_context = state.escape_context();
+    let mut patterns: Vec<String> = vec![];
+    for pattern in &options.patterns {
+        let value = convert_value(pattern, &escape_context);
+        if options.extended && !options.fixed {
+            patterns.extend(split_alternatives(&value));
+        } else {
+            patterns.push(value);
+        }
+    }
+
+    let literal = options.fixed
+        || (!options.word_regexp
+            && patterns
+                .iter()
+                .all(|pattern| is_literal_pattern(pattern, options.extended)));
+
+    // find supports literal patterns for a single input and prints the lines like grep does
+    let use_find = literal
+        && patterns.len() == 1
+        && options.files.len() <= 1
+        && !options.recursive
+        && !options.line_number
+        && !options.files_with_matches
+        && !options.line_regexp;
+
+    let mut windows_arguments = vec![];
+    let windows_command = if use_find {
+        if options.ignore_case {
+            windows_arguments.push("/I".to_string());
+        }
+        if options.invert {
+            windows_arguments.push("/V".to_string());
+        }
+        if options.count {
+            windows_arguments.push("/C".to_string());
+        }
+        windows_arguments.push(format!("\"{}\"", patterns[0].replace('"', "\"\"")));
+        if let Some(file) = options.files.first() {
+            windows_arguments.push(format!("< {}", quote_path(file, state)));
+        }
+
+        format!("find {}", windows_arguments.join(" "))
+    } else {
+        for (enabled, flag) in [
+            (options.recursive, "/S"),
+            (options.ignore_case, "/I"),
+            (options.invert, "/V"),
+            (options.line_number, "/N"),
+            (options.files_with_matches, "/M"),
+            (options.line_regexp, "/X"),
+        ] {
+            if enabled {
+                windows_arguments.push(flag.to_string());
+            }
+        }
+        // whole words are matched with the \< and \> regular expression word positions
+        let regex = !literal || options.word_regexp;
+        windows_arguments.push(if regex { "/R" } else { "/L" }.to_string());
+
+        for pattern in &patterns {
+            let mut findstr_pattern = if !literal {
+                convert_grep_regex(pattern, options.extended, state)
+            } else if regex {
+                pattern
+                    .chars()
+                    .map(|character| match character {
+                        '.' | '[' | ']' | '*' | '^' | '$' | '\\' => format!("\\{}", character),
+                        _ => character.to_string(),
+                    })
+                    .collect()
+            } else {
+                pattern.replace('\\', "\\\\")
+            };
+            if options.word_regexp {
+                findstr_pattern = format!("\\<{}\\>", findstr_pattern);
+            }
+
+            // /C: searches the whole pattern, otherwise findstr searches for any of its words
+            windows_arguments.push(format!("/C:\"{}\"", findstr_pattern.replace('"', "\\\"")));
+        }
+
+        // findstr searches the files matching the provided pattern in all sub directories
+        if options.recursive && options.files.is_empty() {
+            windows_arguments.push("\"*\"".to_string());
+        }
+        for file in &options.files {
+            if options.recursive {
+                let directory = convert_path_value(file, state);
+                windows_arguments.push(format!("\"{}\\*\"", directory.trim_end_matches('\\')));
+            } else {
+                windows_arguments.push(quote_path(file, state));
+            }
+        }
+
+        let mut windows_command = format!("findstr {}", windows_arguments.join(" "));
+        if options.count {
+            windows_command.push_str(" | find /c /v \"\"");
+        }
+
+        windows_command
+    };
+
+    if options.no_messages {
+        redirections.insert(0, "2>nul".to_string());
+    }
+    if options.quiet {
+        redirections.insert(0, ">nul".to_string());
+    }
+
+    append_redirections(windows_command, &redirections)
+}
+
+fn convert_ls(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut all = false;
+    let mut bare = false;
+    let mut recursive = false;
+    let mut reverse = false;
+    let mut sort_order = None;
+    let (options, files) = parse_options(operands, &[]);
+    for option in &options {
+        match option.name.as_str() {
+            "a" | "A" | "all" | "almost-all" => all = true,
+            "1" => bare = true,
+            "R" | "recursive" => recursive = true,
+            "r" | "reverse" => reverse = true,
+            "t" => sort_order = Some('d'),
+            "S" => sort_order = Some('s'),
+            // the long listing with readable sizes is the dir default
+            "l" | "h" | "human-readable" => (),
+            _ => unsupported_option("ls", option, state),
+        }
+    }
+    let paths: Vec<String> = files.iter().map(|file| quote_path(file, state)).collect();
+
+    let mut switches = vec![];
+    if all {
+        switches.push("/a".to_string());
+    }
+    if bare {
+        switches.push("/b".to_string());
+    }
+    if recursive {
+        switches.push("/s".to_string());
+    }
+    // ls sorts the newest and largest first, the name sort is only needed when it is reversed
+    match (sort_order, reverse) {
+        (Some(order), false) => switches.push(format!("/o-{}", order)),
+        (Some(order), true) => switches.push(format!("/o{}", order)),
+        (None, true) => switches.push("/o-n".to_string()),
+        (None, false) => (),
+    }
+
+    let mut windows_command = "dir".to_string();
+    for argument in switches.iter().chain(paths.iter()) {
+        windows_command.push(' ');
+        windows_command.push_str(argument);
+    }
+
+    append_redirections(windows_command, &redirections)
+}
+
+/// Converts a shell directory path to a windows path which ends with a separator.
+fn directory_path(word: &str, state: &State) -> String {
+    let path = convert_path_value(word, state);
+
+    format!("{}\\", path.trim_end_matches('\\'))
+}
+
+/// Returns the last component of a shell path (for example: `b` for `a/b/`).
+fn path_name(word: &str, state: &State) -> String {
+    let path = convert_path_value(word, state);
+    let path = path.trim_end_matches('\\');
+
+    match path.rfind('\\') {
+        Some(index) => path[index + 1..].to_string(),
+        None => path.to_string(),
+    }
+}
+
+/// Groups multiple commands (one per operand) so they are treated as a single command.
+fn group_commands(windows_commands: Vec<String>) -> String {
+    if windows_commands.is_empty() {
+        // cd . is a lightweight no-op which succeeds for && and || (it does not reset ERRORLEVEL)
+        "cd .".to_string()
+    } else if windows_commands.len() == 1 {
+        windows_commands.into_iter().next().unwrap_or_default()
+    } else {
+        format!("({})", windows_commands.join(" & "))
+    }
+}
+
+/// Returns the windows path of a recursive copy source and whether only the directory content
+/// should be copied (the source ends with `/` or `/.`).
+fn copy_source_path(word: &str, state: &State) -> (String, bool) {
+    let copy_content = word.ends_with('/') || word.ends_with("/.");
+    let path = convert_path_value(word, state);
+    let path = path
+        .strip_suffix("\\.")
+        .unwrap_or(&path)
+        .trim_end_matches('\\');
+
+    (path.to_string(), copy_content)
+}
+
+/// Returns the robocopy logging switches, robocopy lists every copied file and a summary by default.
+fn robocopy_logging(verbose: bool) -> Vec<&'static str> {
+    if verbose {
+        vec!["/V"]
+    } else {
+        vec!["/NFL", "/NDL", "/NJH", "/NJS", "/NP"]
+    }
+}
+
+/// Creates a robocopy command which copies the content of the source directory to the target directory.
+///
+/// The paths must not end with a separator since robocopy reads `\"` as an escaped quote.
+fn robocopy(source: &str, target: &str, switches: &[&str]) -> String {
+    format!(
+        "(robocopy \"{}\" \"{}\" {} & {})",
+        source,
+        target,
+        switches.join(" "),
+        ROBOCOPY_ERROR_CHECK
+    )
+}
+
+/// Holds the parsed cp options.
+#[derive(Debug, Default)]
+struct CopyOptions {
+    /// -r or -R
+    recursive: bool,
+    /// -f
+    force: bool,
+    /// -i
+    interactive: bool,
+    /// -n
+    no_clobber: bool,
+    /// -v
+    verbose: bool,
+    /// -p (or -a)
+    preserve: bool,
+    /// -a
+    archive: bool,
+    /// The target directory provided with -t
+    target_directory: Option<String>,
+    /// The sources and the target
+    paths: Vec<String>,
+}
+
+fn parse_cp_options(operands: Vec<String>, state: &mut State) -> CopyOptions {
+    let (command_options, paths) = parse_options(operands, &["t", "target-directory"]);
+    let mut options = CopyOptions {
+        paths,
+        ..CopyOptions::default()
+    };
+
+    for option in command_options {
+        match option.name.as_str() {
+            "r" | "R" | "recursive" => options.recursive = true,
+            "f" | "force" => options.force = true,
+            "i" | "interactive" => options.interactive = true,
+            "n" | "no-clobber" => options.no_clobber = true,
+            "v" | "verbose" => options.verbose = true,
+            // --preserve=mode,ownership,timestamps
+            "p" | "preserve" => options.preserve = true,
+            "a" | "archive" => {
+                options.archive = true;
+                options.recursive = true;
+                options.preserve = true;
+            }
+            "t" | "target-directory" => options.target_directory = option.value,
+            _ => unsupported_option("cp", &option, state),
+        }
+    }
+
+    options
+}
+
+/// Converts cp to copy (for files) or xcopy (for recursive copies).
+///
+/// Multiple sources and targets which end with `/` are copied into the target directory.<br>
+/// A recursive source which ends with `/` or `/.` copies the directory content instead of the directory itself.
+fn convert_cp(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+    let mut options = parse_cp_options(operands, state);
+
+    let (sources, target, into_directory) = match options.target_directory.take() {
+        Some(directory) => (std::mem::take(&mut options.paths), directory, true),
+        None => match options.paths.split_last() {
+            Some((target, sources)) if !sources.is_empty() => (
+                sources.to_vec(),
+                target.to_string(),
+                sources.len() > 1 || target.ends_with('/'),
+            ),
+            _ => {
+                state.add_diagnostic(
+                    Severity::Warning,
+                    "cp without a source and a target is not supported",
+                );
+                let paths: Vec<String> = options
+                    .paths
+                    .iter()
+                    .map(|path| quote_path(path, state))
+                    .collect();
+                let mut windows_command = "copy".to_string();
+                for path in paths {
+                    windows_command.push(' ');
+                    windows_command.push_str(&path);
+                }
+                return append_redirections(windows_command, &redirections);
+            }
+        },
+    };
+
+    let target_directory = directory_path(&target, state);
+    let mut windows_commands = vec![];
+    if options.recursive && state.options.use_robocopy {
+        let mut switches = vec!["/E"];
+        // excludes the changed, newer and older files which already exist in the target
+        if options.no_clobber {
+            switches.extend(["/XC", "/XN", "/XO"]);
+        }
+        if options.archive {
+            switches.push("/DCOPY:T");
+        }
+        switches.extend(robocopy_logging(options.verbose));
+        if options.interactive {
+            state.add_diagnostic(
+                Severity::Warning,
+                "cp -i is not supported by robocopy and existing files are overwritten",
+            );
+        }
+
+        let target_path = target_directory.trim_end_matches('\\');
+        for source in &sources {
+            let (source_path, copy_content) = copy_source_path(source, state);
+
+            // robocopy only copies directories, so files are copied with copy
+            let windows_command = if copy_content {
+                robocopy(&source_path, target_path, &switches)
+            } else {
+                let (directory_target, file_target) = if into_directory {
+                    (
+                        format!("{}\\{}", target_path, path_name(source, state)),
+                        target_directory.clone(),
+                    )
+                } else {
+                    (target_path.to_string(), convert_path_value(&target, state))
+                };
+                format!(
+                    "(if exist \"{source}\\*\" {robocopy} else copy \"{source}\" \"{target}\")",
+                    source = source_path,
+                    robocopy = robocopy(&source_path, &directory_target, &switches),
+                    target = file_target,
+                )
+            };
+            windows_commands.push(windows_command);
+        }
+    } else if options.recursive {
+        let mut switches = vec!["/E", "/I", if options.interactive { "/-Y" } else { "/Y" }];
+        if options.preserve {
+            switches.push("/K");
+        }
+        if options.archive {
+            switches.push("/H");
+        }
+        if options.verbose {
+            switches.push("/F");
+        }
+        if options.no_clobber {
+            state.add_diagnostic(
+                Severity::Warning,
+                "cp -n is not supported by xcopy and existing files are overwritten",
+            );
+        }
+        let xcopy = format!("xcopy {}", switches.join(" "));
+
+        for source in &sources {
+            let (source_path, copy_content) = copy_source_path(source, state);
+
+            // a directory is copied into a new directory with the same name under the target directory
+            let windows_command = if into_directory && !copy_content {
+                format!(
+                    "(if exist \"{source}\\*\" ({xcopy} \"{source}\" \"{target}{name}\\\") else {xcopy} \"{source}\" \"{target}\")",
+                    xcopy = xcopy,
+                    source = source_path,
+                    target = target_directory,
+                    name = path_name(source, state),
+                )
+            } else {
+                format!("{} \"{}\" \"{}\"", xcopy, source_path, target_directory)
+            };
+            windows_commands.push(windows_command);
+        }
+    } else {
+        // copy does not prompt before overwriting files when run from a batch file
+        let copy = if options.interactive {
+            "copy /-Y"
+        } else if options.force {
+            "copy /Y"
+        } else {
+            "copy"
+        };
+
+        for source in &sources {
+            let (target_path, target_file) = if into_directory {
+                (
+                    target_directory.clone(),
+                    format!("{}{}", target_directory, path_name(source, state)),
+                )
+            } else {
+                let target_path = convert_path_value(&target, state);
+                (target_path.clone(), target_path)
+            };
+
+            let mut windows_command =
+                format!("{} {} \"{}\"", copy, quote_path(source, state), target_path);
+            if options.no_clobber {
+                windows_command = format!("if not exist \"{}\" {}", target_file, windows_command);
+            }
+            windows_commands.push(windows_command);
+        }
+    }
+
+    append_redirections(group_commands(windows_commands), &redirections)
+}
+
+/// Returns the drive letter of a windows path (for example: `c` for `c:\work`).
+fn path_drive(path: &str) -> Option<char> {
+    let mut characters = path.chars();
+
+    match (characters.next(), characters.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => Some(drive.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// Converts mv to move.
+///
+/// Multiple sources and targets which end with `/` are moved into the target directory.<br>
+/// move can not move directories between drives, so when enabled, directories are moved with robocopy /MOVE.
+fn convert_mv(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut force = false;
+    let mut interactive = false;
+    let mut no_clobber = false;
+    let mut target_directory = None;
+    let (options, paths) = parse_options(operands, &["t", "target-directory"]);
+    for option in options {
+        // the last of -f, -i and -n wins
+        match option.name.as_str() {
+            "f" | "force" => (force, interactive, no_clobber) = (true, false, false),
+            "i" | "interactive" => (force, interactive, no_clobber) = (false, true, false),
+            "n" | "no-clobber" => (force, interactive, no_clobber) = (false, false, true),
+            // move already prints the moved files
+            "v" | "verbose" => (),
+            "t" | "target-directory" => target_directory = option.value,
+            _ => unsupported_option("mv", &option, state),
+        }
+    }
+
+    let (sources, target, into_directory) = match target_directory {
+        Some(directory) => (paths, directory, true),
+        None => match paths.split_last() {
+            Some((target, sources)) if !sources.is_empty() => (
+                sources.to_vec(),
+                target.to_string(),
+                sources.len() > 1 || target.ends_with('/'),
+            ),
+            _ => {
+                state.add_diagnostic(
+                    Severity::Warning,
+                    "mv without a source and a target is not supported",
+                );
+                return append_redirections("move".to_string(), &redirections);
+            }
+        },
+    };
+
+    let target_directory = directory_path(&target, state);
+    let target_path = if into_directory {
+        target_directory.clone()
+    } else {
+        target_directory.trim_end_matches('\\').to_string()
+    };
+    let move_command = if force {
+        "move /Y"
+    } else if interactive {
+        "move /-Y"
+    } else {
+        "move"
+    };
+
+    let mut robocopy_switches = vec!["/E", "/MOVE"];
+    if no_clobber {
+        robocopy_switches.extend(["/XC", "/XN", "/XO"]);
+    }
+    robocopy_switches.extend(robocopy_logging(false));
+    if interactive && state.options.use_robocopy {
+        state.add_diagnostic(
+            Severity::Warning,
+            "mv -i is not supported by robocopy and existing files are overwritten",
+        );
+    }
+
+    let mut windows_commands = vec![];
+    for source in &sources {
+        let (source_path, _) = copy_source_path(source, state);
+        let target_file = if into_directory {
+            format!("{}{}", target_directory, path_name(source, state))
+        } else {
+            target_path.clone()
+        };
+
+        let mut windows_command =
+            format!("{} \"{}\" \"{}\"", move_command, source_path, target_path);
+        if no_clobber {
+            windows_command = format!("if not exist \"{}\" {}", target_file, windows_command);
+        }
+
+        if state.options.use_robocopy {
+            windows_command = format!(
+                "(if exist \"{}\\*\" {} else {})",
+                source_path,
+                robocopy(&source_path, &target_file, &robocopy_switches),
+                windows_command
+            );
+        } else {
+            let source_drive = path_drive(&source_path);
+            let target_drive = path_drive(&target_path);
+            if (source_drive.is_some() || target_drive.is_some()) && source_drive != target_drive {
+                state.add_diagnostic(
+                    Severity::Warning,
+                    "move can not move directories between drives, enable the robocopy option to support it",
+                );
+            }
+        }
+        windows_commands.push(windows_command);
+    }
+
+    append_redirections(group_commands(windows_commands), &redirections)
+}
+
+/// Converts rm to del, or for recursive removals, to a runtime check which uses rmdir for
+/// directories and del for files.
+fn convert_rm(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut recursive = false;
+    let mut force = false;
+    let mut directory = false;
+    let (options, files) = parse_options(operands, &[]);
+    for option in &options {
+        match option.name.as_str() {
+            "r" | "R" | "recursive" => recursive = true,
+            "f" | "force" => force = true,
+            "d" | "dir" => directory = true,
+            "v" | "verbose" | "preserve-root" | "no-preserve-root" | "one-file-system" => (),
+            _ => unsupported_option("rm", option, state),
+        }
+    }
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| {
+            convert_path_value(file, state)
+                .trim_end_matches('\\')
+                .to_string()
+        })
+        .collect();
+
+    // rm -f without files does nothing, for example: rm -rf "$@"
+    if paths.is_empty() && !force {
+        state.add_diagnostic(Severity::Warning, "rm without files is not supported");
+    }
+
+    let del_command = if force { "del /F /Q" } else { "del" };
+    let windows_command = if recursive || directory {
+        // the directory check is done at runtime since the same path may be a file or a directory
+        let rmdir_command = if recursive { "rmdir /S /Q" } else { "rmdir" };
+        let windows_commands = paths
+            .iter()
+            .map(|path| {
+                // rm -f does not fail for missing files
+                let file_command = if force {
+                    format!("if exist \"{}\" ({} \"{}\")", path, del_command, path)
+                } else {
+                    format!("({} \"{}\")", del_command, path)
+                };
+
+                format!(
+                    "(if exist \"{path}\\*\" ({rmdir} \"{path}\") else {file_command})",
+                    path = path,
+                    rmdir = rmdir_command,
+                    file_command = file_command
+                )
+            })
+            .collect();
+
+        group_commands(windows_commands)
+    } else {
+        let mut windows_command = del_command.to_string();
+        for path in &paths {
+            windows_command.push_str(&format!(" \"{}\"", path));
+        }
+
+        windows_command
+    };
+
+    let mut windows_command = append_redirections(windows_command, &redirections);
+    // errors are suppressed and cd . makes the command succeed for && and ||
+    // (it does not reset ERRORLEVEL)
+    if force {
+        windows_command.push_str(" 2>nul || cd .");
+    }
+
+    windows_command
+}
+
+/// Converts mkdir, with -p the directories are created only if they do not exist.
+///
+/// cmd mkdir always creates the missing parent directories.
+fn convert_mkdir(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut parents = false;
+    let (options, files) = parse_options(operands, &["m", "mode"]);
+    for option in &options {
+        match option.name.as_str() {
+            "p" | "parents" => parents = true,
+            "v" | "verbose" => (),
+            "m" | "mode" => state.add_diagnostic(
+                Severity::Info,
+                "mkdir -m is not supported and the directory mode is ignored",
+            ),
+            _ => unsupported_option("mkdir", option, state),
+        }
+    }
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| {
+            convert_path_value(file, state)
+                .trim_end_matches('\\')
+                .to_string()
+        })
+        .collect();
+
+    let windows_command = if parents {
+        let windows_commands = paths
+            .iter()
+            .map(|path| format!("if not exist \"{}\\\" mkdir \"{}\"", path, path))
+            .collect();
+
+        group_commands(windows_commands)
+    } else {
+        let mut windows_command = "mkdir".to_string();
+        for path in &paths {
+            windows_command.push_str(&format!(" \"{}\"", path));
+        }
+
+        windows_command
+    };
+
+    append_redirections(windows_command, &redirections)
+}
+
+/// Converts touch to a copy which updates the modification time of existing files
+/// and creates the missing files (unless -c is provided).
+fn convert_touch(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut no_create = false;
+    let (options, files) = parse_options(operands, &["d", "t", "r", "date", "reference"]);
+    for option in &options {
+        match option.name.as_str() {
+            "c" | "no-create" => no_create = true,
+            // copy updates the modification time
+            "m" => (),
+            "a" => state.add_diagnostic(
+                Severity::Warning,
+                "touch -a is not supported and the modification time is updated instead",
+            ),
+            // options with a time value
+            "d" | "t" | "r" | "date" | "reference" => state.add_diagnostic(
+                Severity::Warning,
+                &format!(
+                    "touch {} is not supported and the current time is used",
+                    option
+                ),
+            ),
+            _ => unsupported_option("touch", option, state),
+        }
+    }
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| convert_path_value(file, state))
+        .collect();
+
+    let windows_commands = paths
+        .iter()
+        .map(|path| {
+            // copy with the +,, suffix only updates the file modification time
+            let update_command = format!("copy /B \"{path}\"+,, \"{path}\" >nul", path = path);
+
+            if no_create {
+                format!("if exist \"{}\" {}", path, update_command)
+            } else {
+                format!(
+                    "(if exist \"{path}\" ({update}) else (type nul >> \"{path}\"))",
+                    path = path,
+                    update = update_command
+                )
+            }
+        })
+        .collect();
+
+    append_redirections(group_commands(windows_commands), &redirections)
+}
+
+/// Returns true if the windows path is absolute (or starts with a variable, which usually holds an absolute path).
+fn is_absolute_path(path: &str) -> bool {
+    path.starts_with('\\') || path.starts_with('%') || path_drive(path).is_some()
+}
+
+/// Converts ln to mklink, note that mklink expects the link before the target.
+///
+/// Symbolic links to directories need the /D switch, which is used if the target ends with `/`,
+/// skipped if the target name has an extension and otherwise is decided at runtime.
+fn convert_ln(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    let mut symbolic = false;
+    let mut force = false;
+    let (options, paths) = parse_options(operands, &[]);
+    for option in &options {
+        match option.name.as_str() {
+            "s" | "symbolic" => symbolic = true,
+            "f" | "force" => force = true,
+            "n" | "no-dereference" | "v" | "verbose" | "T" | "no-target-directory" => (),
+            _ => unsupported_option("ln", option, state),
+        }
+    }
+
+    // without a link name or with a link directory, the link is named after the target
+    let links: Vec<(String, String)> = match paths.split_last() {
+        None => {
+            state.add_diagnostic(Severity::Warning, "ln without a target is not supported");
+            return append_redirections("mklink".to_string(), &redirections);
+        }
+        Some((target, [])) => vec![(target.to_string(), path_name(target, state))],
+        Some((link, targets)) if targets.len() > 1 || link.ends_with('/') => {
+            let link_directory = directory_path(link, state);
+            targets
+                .iter()
+                .map(|target| {
+                    let link_path = format!("{}{}", link_directory, path_name(target, state));
+                    (target.to_string(), link_path)
+                })
+                .collect()
+        }
+        Some((link, targets)) => {
+            let link_path = convert_path_value(link, state);
+            vec![(
+                targets[0].to_string(),
+                link_path.trim_end_matches('\\').to_string(),
+            )]
+        }
+    };
+
+    if symbolic {
+        state.add_diagnostic(
+            Severity::Info,
+            "mklink requires Developer Mode or administrator rights to create symbolic links",
+        );
+    }
+
+    let mut windows_commands = vec![];
+    for (target, link) in links {
+        let target_path = convert_path_value(&target, state);
+        let target_path = target_path.trim_end_matches('\\');
+        let mklink =
+            |switch: &str| format!("mklink{} \"{}\" \"{}\" >nul", switch, link, target_path);
+
+        let target_name = path_name(&target, state);
+        let mut windows_command = if !symbolic {
+            mklink(" /H")
+        } else if target.ends_with('/') {
+            mklink(" /D")
+        } else if target_name.trim_start_matches('.').contains('.') {
+            mklink("")
+        } else {
+            // relative targets are relative to the link directory
+            let target_check = match link.rfind('\\') {
+                Some(index) if !is_absolute_path(target_path) => {
+                    format!("{}\\{}", &link[..index], target_path)
+                }
+                _ => target_path.to_string(),
+            };
+
+            format!(
+                "(if exist \"{}\\*\" ({}) else ({}))",
+                target_check,
+                mklink(" /D"),
+                mklink("")
+            )
+        };
+
+        // directory links are removed with rmdir, which does not remove the target content
+        if force {
+            windows_command = format!(
+                "((if exist \"{link}\\*\" (rmdir \"{link}\") else if exist \"{link}\" (del /F /Q \"{link}\")) & {command})",
+                link = link,
+                command = windows_command
+            );
+        }
+        windows_commands.push(windows_command);
+    }
+
+    append_redirections(group_commands(windows_commands), &redirections)
+}
+
+/// Converts the commands which find programs (which, command -v, type and hash) to where.
+fn convert_which(command: &str, arguments: &[String], state: &mut State) -> String {
+    let (operands, mut redirections) = split_redirections(arguments, state);
+
+    // hash only sets the exit code
+    let mut silent = command == "hash";
+    let (options, names) = parse_options(operands, &[]);
+    for option in &options {
+        match option.name.as_str() {
+            "s" if command == "which" => silent = true,
+            // where prints all the matching paths
+            "a" | "p" | "P" | "v" | "V" => (),
+            _ => unsupported_option(command, option, state),
+        }
+    }
+    let names: Vec<String> = names
+        .iter()
+        .map(|name| convert_text(name, &state.escape_context()))
+        .collect();
+
+    if names.is_empty() {
+        state.add_diagnostic(
+            Severity::Warning,
+            &format!("{} without a command name is not supported", command),
+        );
+    }
+
+    // where prints an error when the program is not found, unlike the shell commands
+    let output_discarded = redirections
+        .iter()
+        .any(|redirection| redirection.starts_with(">nul") || redirection.starts_with("1>nul"));
+    let error_redirected = redirections
+        .iter()
+        .any(|redirection| redirection.starts_with("2>") || redirection.ends_with("2>&1"));
+    if silent && !output_discarded {
+        redirections.insert(0, ">nul".to_string());
+    }
+    if (silent || output_discarded) && !error_redirected {
+        redirections.push("2>&1".to_string());
+    }
+
+    let mut windows_command = "where".to_string();
+    for name in names {
+        windows_command.push(' ');
+        windows_command.push_str(&name);
+    }
+
+    append_redirections(windows_command, &redirections)
+}
+
+/// Converts sleep to timeout, the duration is rounded to whole seconds.
+fn convert_sleep(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
+
+    // the durations are added together, each may have a unit suffix (s, m, h or d)
+    let mut seconds = 0.0;
+    let mut dynamic_durations = vec![];
+    for operand in &operands {
+        let (number, multiplier) = match operand.chars().last() {
+            Some('s') => (&operand[..operand.len() - 1], 1.0),
+            Some('m') => (&operand[..operand.len() - 1], 60.0),
+            Some('h') => (&operand[..operand.len() - 1], 3600.0),
+            Some('d') => (&operand[..operand.len() - 1], 86400.0),
+            _ => (operand.as_str(), 1.0),
+        };
+
+        match number.parse::<f64>() {
+            Ok(value) if value.is_finite() => seconds += value * multiplier,
+            _ if operand == "infinity" => seconds = f64::INFINITY,
+            _ => dynamic_durations.push(convert_text(operand, &state.escape_context())),
+        }
+    }
+
+    let duration = if seconds.is_infinite() {
+        // timeout waits until a key is pressed
+        "-1".to_string()
+    } else if !dynamic_durations.is_empty() {
+        if dynamic_durations.len() > 1 || seconds != 0.0 {
+            state.add_diagnostic(
+                Severity::Warning,
+                "sleep with multiple durations which are not numbers is not supported, only the first is used",
+            );
+        }
+        dynamic_durations.remove(0)
+    } else {
+        let rounded = seconds.round();
+        if rounded != seconds {
+            state.add_diagnostic(
+                Severity::Warning,
+                &format!(
+                    "timeout only supports whole seconds, sleep {} is rounded to {} seconds",
+                    operands.join(" "),
+                    rounded
+                ),
+            );
+        }
+        rounded.to_string()
+    };
 
-    for flags in flags_mappings {
-        let (shell_flag, windows_flag) = flags;
+    if operands.is_empty() {
+        state.add_diagnostic(
+            Severity::Warning,
+            "sleep without a duration is not supported",
+        );
+    }
 
-        windows_arguments = match Regex::new(shell_flag) {
-            Ok(shell_regex) => {
-                let str_value = &shell_regex.replace_all(&windows_arguments, windows_flag);
-                str_value.to_string()
-            }
-            Err(_) => windows_arguments,
+    append_redirections(
+        format!("timeout /t {} /nobreak >nul", duration),
+        &redirections,
+    )
+}
+
+fn is_chmod_mode(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|character| "ugoarwxXst+-=,01234567".contains(character))
+}
+
+/// Returns true if the symbolic mode makes the files read only, false if it makes them writable
+/// and None if the owner write permission is not changed.
+fn chmod_read_only(mode: &str) -> Option<bool> {
+    let mut read_only = None;
+
+    for clause in mode.split(',') {
+        let operator_index = match clause.find(['+', '-', '=']) {
+            Some(index) => index,
+            None => continue,
         };
+        let who = &clause[..operator_index];
+        if !who.is_empty() && !who.contains(['u', 'a']) {
+            continue;
+        }
+
+        let mut operator = '+';
+        let mut permissions = String::new();
+        for character in clause[operator_index..].chars().chain([',']) {
+            if "+-=,".contains(character) {
+                if !permissions.is_empty() || operator == '=' {
+                    match operator {
+                        '=' => read_only = Some(!permissions.contains('w')),
+                        _ if permissions.contains('w') => read_only = Some(operator == '-'),
+                        _ => (),
+                    }
+                }
+                operator = character;
+                permissions.clear();
+            } else {
+                permissions.push(character);
+            }
+        }
     }
 
-    windows_arguments
+    read_only
 }
 
-fn convert_var<'a>(value: &'a str, buffer: &mut Vec<&'a str>) {
-    // Batch file vars have one of two forms: `%NAME%` (corresponding to regular variables),
-    // or `%n` if `n` is a digit in the range 0 to 9 or an `*` (corresponding to input params).
-    match value {
-        "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => {
-            buffer.push("%");
-            buffer.push(value);
+/// Converts chmod write permission changes to attrib, other permissions have no windows equivalent.
+fn convert_chmod(arguments: &[String], state: &mut State) -> String {
+    let (mut operands, redirections) = split_redirections(arguments, state);
+
+    // modes such as -w start with a hyphen as well, so the mode is removed before the options are parsed
+    let mode = match operands
+        .iter()
+        .position(|operand| !operand.starts_with('-') || is_chmod_mode(operand) || operand == "--")
+    {
+        Some(index) if operands[index] == "--" && index + 1 < operands.len() => {
+            operands.remove(index);
+            operands.remove(index)
         }
-        "@" => buffer.push("%*"),
+        Some(index) if operands[index] != "--" => operands.remove(index),
+        _ => String::new(),
+    };
+
+    let (options, files) = parse_options(operands, &[]);
+    for option in &options {
+        match option.name.as_str() {
+            "R" | "recursive" => state.add_diagnostic(
+                Severity::Warning,
+                "chmod -R is not supported and only the provided paths are changed",
+            ),
+            // output and error reporting options
+            "c" | "f" | "v" | "changes" | "silent" | "quiet" | "verbose" => (),
+            _ => unsupported_option("chmod", option, state),
+        }
+    }
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| convert_path_value(file, state))
+        .collect();
+
+    let read_only = match chmod_read_only(&mode) {
+        Some(read_only) if !paths.is_empty() => read_only,
         _ => {
-            buffer.push("%");
-            buffer.push(value);
-            buffer.push("%");
+            state.add_diagnostic(
+                Severity::Info,
+                &format!("chmod {} has no windows equivalent and is ignored", mode),
+            );
+            return String::new();
         }
+    };
+
+    if mode.contains(['r', 'x', 'X', 's', 't']) {
+        state.add_diagnostic(
+            Severity::Info,
+            "chmod only converts the write permission, other permissions are ignored",
+        );
     }
-}
 
-fn replace_full_vars(arguments: &str) -> String {
-    let mut parts: Vec<&str> = arguments.split("${").collect();
-    let mut buffer = vec![];
+    let attribute = if read_only { "+R" } else { "-R" };
+    let windows_commands = paths
+        .iter()
+        .map(|path| format!("attrib {} \"{}\"", attribute, path))
+        .collect();
 
-    buffer.push(parts.remove(0));
+    append_redirections(group_commands(windows_commands), &redirections)
+}
 
-    for part in parts {
-        let (before, after, found) = match part.find("}") {
-            None => (part, "", false),
-            Some(index) => {
-                let values = part.split_at(index);
+/// Removes the sudo prefix and its options and converts the wrapped command.
+fn convert_sudo(arguments: &[String], state: &mut State) -> String {
+    state.add_diagnostic(
+        Severity::Warning,
+        "sudo is not supported and the command is run without elevation",
+    );
 
-                (values.0, &values.1[1..values.1.len()], true)
+    let mut arguments = arguments.iter();
+    let mut command = vec![];
+    while let Some(argument) = arguments.next() {
+        if argument == "--" {
+            command.extend(arguments.by_ref());
+        } else if let Some(long_option) = argument.strip_prefix("--") {
+            // long options with a value which is not part of the word
+            let value_options = [
+                "chdir",
+                "close-from",
+                "command-timeout",
+                "group",
+                "host",
+                "other-user",
+                "prompt",
+                "role",
+                "type",
+                "user",
+            ];
+            if value_options.contains(&long_option) {
+                arguments.next();
+            }
+        } else if let Some(flags) = argument.strip_prefix('-') {
+            // options with a value, which is the rest of the word or the next word
+            if let Some(index) = flags.find(['C', 'D', 'g', 'h', 'p', 'r', 't', 'T', 'u', 'U']) {
+                if index == flags.len() - 1 {
+                    arguments.next();
+                }
             }
-        };
-
-        if found {
-            convert_var(before, &mut buffer);
         } else {
-            buffer.push(before)
-        }
-
-        if after.len() > 0 {
-            buffer.push(after);
+            command.push(argument);
+            command.extend(arguments.by_ref());
         }
     }
 
-    buffer.join("").to_string()
+    if command.is_empty() {
+        String::new()
+    } else {
+        let command: Vec<&str> = command.iter().map(|word| word.as_str()).collect();
+        convert_line(&command.join(" "), state)
+    }
 }
 
-fn replace_partial_vars(arguments: &str) -> String {
-    let mut parts: Vec<&str> = arguments.split('$').collect();
-    let mut buffer = vec![];
+/// Converts local rsync copies to robocopy.
+///
+/// Like rsync, a source which ends with `/` copies the directory content instead of the directory itself.
+fn convert_rsync(arguments: &[String], state: &mut State) -> String {
+    let (operands, redirections) = split_redirections(arguments, state);
 
-    buffer.push(parts.remove(0));
+    let mut recursive = false;
+    let mut verbose = false;
+    let mut switches = vec![];
+    let (options, paths) = parse_options(operands, &["exclude"]);
+    for option in &options {
+        match (option.name.as_str(), &option.value) {
+            ("a" | "archive" | "r" | "recursive", _) => recursive = true,
+            ("v" | "verbose", _) => verbose = true,
+            ("n" | "dry-run", _) => switches.push("/L".to_string()),
+            ("u" | "update", _) => switches.push("/XO".to_string()),
+            ("delete", _) => switches.push("/PURGE".to_string()),
+            // robocopy keeps the attributes and times and the rest has no meaning for local copies
+            (
+                "t" | "p" | "o" | "g" | "l" | "D" | "z" | "h" | "P" | "q" | "times" | "perms"
+                | "compress" | "human-readable" | "progress" | "quiet",
+                _,
+            ) => (),
+            ("exclude", Some(pattern)) => {
+                let pattern = convert_value(pattern, &state.escape_context());
+                switches.push(format!("/XF \"{}\" /XD \"{}\"", pattern, pattern));
+            }
+            _ => unsupported_option("rsync", option, state),
+        }
+    }
 
-    for part in parts {
-        let (before, after) = match part.find(" ") {
-            None => (part, ""),
-            Some(index) => part.split_at(index),
-        };
+    let (target, sources) = match paths.split_last() {
+        Some((target, sources)) if !sources.is_empty() => (target, sources),
+        _ => {
+            state.add_diagnostic(
+                Severity::Warning,
+                "rsync without a source and a target is not supported",
+            );
+            return append_redirections("robocopy".to_string(), &redirections);
+        }
+    };
+    if paths.iter().any(|path| {
+        path.find(':')
+            .map(|index| index > 1 && !path[..index].contains('/'))
+            .unwrap_or(false)
+    }) {
+        state.add_diagnostic(Severity::Warning, "rsync to remote hosts is not supported");
+    }
 
-        convert_var(before, &mut buffer);
+    let mut robocopy_switches: Vec<&str> = if recursive { vec!["/E"] } else { vec![] };
+    robocopy_switches.extend(switches.iter().map(|switch| switch.as_str()));
+    robocopy_switches.extend(robocopy_logging(verbose));
 
-        if after.len() > 0 {
-            buffer.push(after);
-        }
+    let target_path = directory_path(target, state);
+    let target_path = target_path.trim_end_matches('\\');
+    let mut windows_commands = vec![];
+    for source in sources {
+        let (source_path, copy_content) = copy_source_path(source, state);
+
+        let windows_command = if copy_content {
+            robocopy(&source_path, target_path, &robocopy_switches)
+        } else {
+            format!(
+                "(if exist \"{source}\\*\" {robocopy} else copy \"{source}\" \"{target}\")",
+                source = source_path,
+                robocopy = robocopy(
+                    &source_path,
+                    &format!("{}\\{}", target_path, path_name(source, state)),
+                    &robocopy_switches
+                ),
+                target = target_path,
+            )
+        };
+        windows_commands.push(windows_command);
     }
 
-    buffer.join("").to_string()
+    append_redirections(group_commands(windows_commands), &redirections)
 }
 
-fn replace_vars(arguments: &str) -> String {
-    let mut updated_arguments = replace_full_vars(arguments);
-    updated_arguments = replace_partial_vars(&updated_arguments);
+/// Converts commands which need to handle each of their arguments separately.
+fn convert_command(line: &str, state: &mut State) -> Option<String> {
+    let words = parser::split_words(line);
+    let (shell_command, arguments) = words.split_first()?;
 
-    updated_arguments
+    match shell_command.as_str() {
+        // cd . is a lightweight no-op which succeeds for && and || (it does not reset ERRORLEVEL)
+        ":" | "true" => {
+            let (_, redirections) = split_redirections(arguments, state);
+            Some(append_redirections("cd .".to_string(), &redirections))
+        }
+        "false" => {
+            let (_, redirections) = split_redirections(arguments, state);
+            Some(append_redirections(
+                "cmd /c exit 1".to_string(),
+                &redirections,
+            ))
+        }
+        "cat" => Some(convert_cat(arguments, state)),
+        "command" => match arguments.first().map(|argument| argument.as_str()) {
+            Some("-v" | "-V") => Some(convert_which("command", arguments, state)),
+            // command runs the provided command without looking up shell functions
+            Some(_) => Some(convert_line(&arguments.join(" "), state)),
+            None => None,
+        },
+        "cd" => Some(convert_cd(arguments, state)),
+        "chmod" => Some(convert_chmod(arguments, state)),
+        "chgrp" | "chown" => {
+            state.add_diagnostic(
+                Severity::Info,
+                &format!("{} has no windows equivalent and is ignored", shell_command),
+            );
+            Some(String::new())
+        }
+        "cp" => Some(convert_cp(arguments, state)),
+        "echo" => Some(convert_echo(arguments, state)),
+        "grep" => Some(convert_grep(arguments, state)),
+        "hash" | "type" | "which" => Some(convert_which(shell_command, arguments, state)),
+        "head" => Some(convert_head(arguments, state)),
+        "ln" => Some(convert_ln(arguments, state)),
+        "ls" => Some(convert_ls(arguments, state)),
+        "mkdir" => Some(convert_mkdir(arguments, state)),
+        "mv" => Some(convert_mv(arguments, state)),
+        "printf" => Some(convert_printf(arguments, state)),
+        "rm" => Some(convert_rm(arguments, state)),
+        "rsync" => Some(convert_rsync(arguments, state)),
+        "sleep" => Some(convert_sleep(arguments, state)),
+        "sudo" => Some(convert_sudo(arguments, state)),
+        "touch" => Some(convert_touch(arguments, state)),
+        "pushd" => Some(convert_pushd(arguments, state)),
+        "popd" => {
+            let (_, redirections) = split_redirections(arguments, state);
+            Some(append_redirections("popd".to_string(), &redirections))
+        }
+        "tail" => Some(convert_tail(arguments, state)),
+        "wc" => Some(convert_wc(arguments, state)),
+        _ => None,
+    }
 }
 
 fn add_arguments(arguments: &str, additional_arguments: Vec<String>, pre: bool) -> String {
@@ -117,8 +2628,8 @@ fn add_arguments(arguments: &str, additional_arguments: Vec<String>, pre: bool)
     }
 
     if pre {
-        if arguments.len() > 0 {
-            windows_arguments.push_str(" ");
+        if !arguments.is_empty() {
+            windows_arguments.push(' ');
         }
         windows_arguments.push_str(arguments);
     }
@@ -126,16 +2637,445 @@ fn add_arguments(arguments: &str, additional_arguments: Vec<String>, pre: bool)
     windows_arguments.trim_start().to_string()
 }
 
-fn convert_line(line: &str) -> String {
+fn convert_assignments(line: &str, state: &State) -> Option<String> {
+    let words = parser::split_words(line);
+    let shell_command = words.first().map(|word| word.as_str()).unwrap_or("");
+
+    let assignment_words = match shell_command {
+        "export" | "readonly" | "declare" | "local" => &words[1..],
+        _ => {
+            // a line made only of assignments, for example: A=1 B=2
+            if words
+                .iter()
+                .all(|word| parser::split_assignment(word).is_some())
+            {
+                &words[..]
+            } else {
+                return None;
+            }
+        }
+    };
+
+    // flags and names without a value (for example: export A) have no batch equivalent
+    // since all batch variables are visible to child processes.
+    let set_commands: Vec<String> = assignment_words
+        .iter()
+        .filter(|word| !word.starts_with('-'))
+        .filter_map(|word| parser::split_assignment(word))
+        .map(|(name, value)| {
+            format!(
+                "set \"{}={}\"",
+                name,
+                convert_value(value, &state.escape_context())
+            )
+        })
+        .collect();
+
+    Some(set_commands.join("\n"))
+}
+
+fn convert_env_prefix(line: &str, state: &mut State) -> Option<String> {
+    let words = parser::split_words(line);
+    let env_command = words.first().map(|word| word == "env").unwrap_or(false);
+
+    let mut set_commands = vec![];
+    let mut command_index = if env_command { 1 } else { 0 };
+    // env options come before the assignments
+    while env_command && command_index < words.len() {
+        let word = &words[command_index];
+        if word == "--" {
+            command_index += 1;
+            break;
+        } else if word == "-" || word == "-i" || word == "--ignore-environment" {
+            state.add_diagnostic(
+                Severity::Warning,
+                "env -i is not supported and the current environment is kept",
+            );
+        } else if let Some(name) = word
+            .strip_prefix("--unset=")
+            .or_else(|| word.strip_prefix("-u").filter(|name| !name.is_empty()))
+        {
+            set_commands.push(format!("set {}=", name));
+        } else if word == "-u" || word == "--unset" {
+            command_index += 1;
+            if let Some(name) = words.get(command_index) {
+                set_commands.push(format!("set {}=", name));
+            }
+        } else if word.starts_with('-') {
+            state.add_diagnostic(
+                Severity::Warning,
+                &format!("env option {} is not supported and is ignored", word),
+            );
+        } else {
+            break;
+        }
+        command_index += 1;
+    }
+
+    while let Some((name, value)) = words
+        .get(command_index)
+        .and_then(|word| parser::split_assignment(word))
+    {
+        set_commands.push(format!(
+            "set \"{}={}\"",
+            name,
+            convert_value(value, &state.escape_context())
+        ));
+        command_index += 1;
+    }
+
+    if !env_command && set_commands.is_empty() {
+        return None;
+    }
+
+    // env without a command prints the environment
+    let windows_command = if command_index < words.len() {
+        convert_line(&words[command_index..].join(" "), state)
+    } else {
+        "set".to_string()
+    };
+
+    if set_commands.is_empty() {
+        return Some(windows_command);
+    }
+
+    // endlocal would undo the directory change, so the variables are not set for these commands
+    if let Some(builtin @ ("cd" | "pushd" | "popd")) =
+        words.get(command_index).map(|word| word.as_str())
+    {
+        state.add_diagnostic(
+            Severity::Warning,
+            &format!(
+                "variables assigned for {} are not supported and are ignored",
+                builtin
+            ),
+        );
+        return Some(windows_command);
+    }
+
+    // cmd /c scopes the variables to the wrapped command and keeps its exit code
+    if state.conditional_command {
+        set_commands.push(windows_command);
+        return Some(format!("cmd /c \"{}\"", set_commands.join(" & ")));
+    }
+
+    // setlocal/endlocal scope the variables to the wrapped command only
+    let mut windows_commands = vec!["setlocal".to_string()];
+    windows_commands.extend(set_commands);
+    windows_commands.push(windows_command);
+    windows_commands.push("endlocal".to_string());
+
+    Some(windows_commands.join(" & "))
+}
+
+/// Applies a single set option and returns the echo mode if the option changes it.
+fn apply_set_option(name: &str, enable: bool, state: &mut State) -> Option<bool> {
+    match name {
+        "errexit" => state.errexit = enable,
+        // echo on is the closest thing batch has for tracing the executed commands
+        "xtrace" | "verbose" => return Some(enable),
+        "nounset" | "pipefail" => state.add_diagnostic(
+            Severity::Warning,
+            &format!("set option {} has no batch equivalent and is ignored", name),
+        ),
+        _ => state.add_diagnostic(
+            Severity::Warning,
+            &format!("unsupported set option {} is ignored", name),
+        ),
+    }
+
+    None
+}
+
+fn convert_set(line: &str, state: &mut State) -> Option<String> {
+    let words = parser::split_words(line);
+
+    if words.first().map(|word| word != "set").unwrap_or(true) {
+        return None;
+    }
+
+    // set without arguments prints all variables
+    if words.len() == 1 {
+        return Some("set".to_string());
+    }
+
+    let mut echo = None;
+    let mut index = 1;
+    while index < words.len() {
+        let word = &words[index];
+        index += 1;
+
+        let enable = match word.chars().next() {
+            Some('-') if word != "-" && word != "--" => true,
+            Some('+') if word != "+" => false,
+            _ => {
+                state.add_diagnostic(
+                    Severity::Warning,
+                    "setting positional parameters is not supported and is ignored",
+                );
+                break;
+            }
+        };
+
+        // options can be combined, for example: set -euo pipefail
+        for option in word.chars().skip(1) {
+            let name = match option {
+                'e' => "errexit",
+                'x' => "xtrace",
+                'v' => "verbose",
+                'u' => "nounset",
+                'o' => match words.get(index) {
+                    Some(long_name) => {
+                        index += 1;
+                        long_name
+                    }
+                    None => {
+                        state.add_diagnostic(
+                            Severity::Warning,
+                            "printing the set options is not supported and is ignored",
+                        );
+                        continue;
+                    }
+                },
+                _ => {
+                    state.add_diagnostic(
+                        Severity::Warning,
+                        &format!("unsupported set option {}{} is ignored", &word[..1], option),
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(echo_value) = apply_set_option(name, enable, state) {
+                echo = Some(echo_value);
+            }
+        }
+    }
+
+    let windows_command = match echo {
+        Some(true) => "@echo on",
+        Some(false) => "@echo off",
+        None => "",
+    };
+
+    Some(windows_command.to_string())
+}
+
+/// Returns true if one of the commands in the line is part of a compound command (for example: `if` or `while`).
+fn is_compound_command(line: &str) -> bool {
+    parser::split_list(line).iter().any(|(command, _)| {
+        parser::split_words(command)
+            .first()
+            .map(|word| COMPOUND_COMMAND_WORDS.contains(&word.as_str()))
+            .unwrap_or(false)
+    })
+}
+
+/// Returns true if the provided shell line runs a command which may fail.
+fn is_command(line: &str) -> bool {
+    if line.contains(SHELL2BATCH_PREFIX) {
+        return true;
+    }
+
+    let words = parser::split_words(line);
+    match words.first().map(|word| word.as_str()) {
+        None => false,
+        Some(word) if word.starts_with('#') => false,
+        Some("(" | "{") => false,
+        // the end of a multi line group, the commands inside it are already checked
+        Some(word) if word.starts_with(')') || word.starts_with('}') => false,
+        Some(_) if is_compound_command(line) => false,
+        Some("set" | "export" | "readonly" | "declare" | "local" | "unset" | "exit" | "return") => {
+            false
+        }
+        _ => !words
+            .iter()
+            .all(|word| parser::split_assignment(word).is_some()),
+    }
+}
+
+/// Converts the start and end lines of groups which span multiple lines.
+fn convert_block_line(line: &str, state: &mut State) -> Option<String> {
+    match line {
+        "(" => {
+            state.blocks.push('(');
+            Some(format!("{} & (", SUBSHELL_START))
+        }
+        "{" => {
+            state.blocks.push('{');
+            Some("(".to_string())
+        }
+        _ if line.starts_with(')') || line.starts_with('}') => {
+            let group_type = if line.starts_with(')') { '(' } else { '{' };
+            if state.blocks.last() != Some(&group_type) {
+                return None;
+            }
+            state.blocks.pop();
+
+            let words = parser::split_words(&line[1..]);
+            let (_, redirections) = split_redirections(&words, state);
+            let mut windows_command = append_redirections(")".to_string(), &redirections);
+            if group_type == '(' {
+                windows_command.push_str(" & ");
+                windows_command.push_str(SUBSHELL_END);
+            }
+
+            Some(windows_command)
+        }
+        _ => None,
+    }
+}
+
+/// Converts a subshell or a brace group which starts and ends in the same line.
+///
+/// Standalone subshells are scoped with setlocal/endlocal (which keeps the variables)
+/// and pushd/popd (which keeps the current directory).<br>
+/// If the subshell exit code is used by `&&` or `||`, it is run with `cmd /c` instead and
+/// piped subshells are already run by cmd in a separate process.
+fn convert_group(
+    group_type: char,
+    inner: &str,
+    rest: &str,
+    operators: (&str, &str),
+    state: &mut State,
+) -> String {
+    let words = parser::split_words(rest);
+    let (_, redirections) = split_redirections(&words, state);
+
+    let is_operator = |value: &str| operators.0 == value || operators.1 == value;
+    let piped = is_operator("|");
+    let use_cmd = group_type == '(' && !piped && state.conditional_command;
+
+    if !use_cmd {
+        state.blocks.push(group_type);
+    }
+    let inner_command = convert_line(inner, state).replace('\n', " & ");
+    if !use_cmd {
+        state.blocks.pop();
+    }
+
+    if use_cmd {
+        append_redirections(format!("cmd /c \"{}\"", inner_command), &redirections)
+    } else {
+        let block = append_redirections(format!("({})", inner_command), &redirections);
+
+        if group_type == '(' && !piped {
+            format!("{} & {} & {}", SUBSHELL_START, block, SUBSHELL_END)
+        } else {
+            block
+        }
+    }
+}
+
+/// Converts a command list (commands joined with `&&`, `||`, `|`, `;` or `&`) and groups.
+///
+/// Returns None if the line holds a single simple command.
+fn convert_list(line: &str, state: &mut State) -> Option<String> {
+    let items = parser::split_list(line);
+    let single = items.len() == 1 && items[0].1.is_empty();
+    if single && parser::split_group(&items[0].0).is_none() {
+        return None;
+    }
+    let list_error_check = std::mem::take(&mut state.list_error_check);
+
+    let is_conditional = |operator: &str| operator == "&&" || operator == "||" || operator == "|";
+    let mut windows_command = String::new();
+    let mut previous_operator = "";
+    let mut and_or_list = false;
+    for (index, (command, operator)) in items.iter().enumerate() {
+        // the last command of a list which is followed by ; or & (or ends the line)
+        let list_end = !is_conditional(operator);
+        state.conditional_command = is_conditional(previous_operator)
+            || is_conditional(operator)
+            || (list_error_check && list_end);
+        let mut converted_command = match parser::split_group(command) {
+            Some((group_type, inner, rest)) => convert_group(
+                group_type,
+                &inner,
+                &rest,
+                (previous_operator, operator),
+                state,
+            ),
+            None => convert_line(command, state).replace('\n', " & "),
+        };
+        state.conditional_command = false;
+
+        // commands which have no windows equivalent are replaced with a no-op
+        if converted_command.is_empty() && !single {
+            converted_command = "cd .".to_string();
+        }
+
+        // set -e ignores failures of all the commands in an and/or list except the last one
+        and_or_list = and_or_list || previous_operator == "&&" || previous_operator == "||";
+        if list_error_check && list_end {
+            converted_command = if and_or_list {
+                format!("({} {})", converted_command, ERROR_EXIT)
+            } else {
+                format!("{} {}", converted_command, ERROR_EXIT)
+            };
+            and_or_list = false;
+        }
+        windows_command.push_str(&converted_command);
+
+        if operator == "&" {
+            state.add_diagnostic(
+                Severity::Warning,
+                "background commands are not supported and are run in the foreground",
+            );
+        }
+
+        if index < items.len() - 1 {
+            let windows_operator = match operator.as_str() {
+                ";" | "&" => "&",
+                _ => operator,
+            };
+            windows_command.push_str(&format!(" {} ", windows_operator));
+        }
+
+        previous_operator = operator;
+    }
+
+    Some(windows_command)
+}
+
+fn convert_line(line: &str, state: &mut State) -> String {
     if line.contains(SHELL2BATCH_PREFIX) {
         let index = line.find(SHELL2BATCH_PREFIX).unwrap() + SHELL2BATCH_PREFIX.len();
         let windows_command = line[index..].trim();
+
+        // custom commands may toggle delayed expansion which affects how we escape
+        let lowercase_command = windows_command.to_lowercase();
+        if lowercase_command.contains("enabledelayedexpansion") {
+            state.delayed_expansion = true;
+        } else if lowercase_command.contains("disabledelayedexpansion") {
+            state.delayed_expansion = false;
+        }
+
         windows_command.to_string()
     } else if line.starts_with("#") {
         let mut windows_command = String::from(line);
         windows_command.remove(0);
         windows_command.insert_str(0, "@REM ");
 
+        windows_command
+    } else if is_compound_command(line) {
+        state.add_diagnostic(
+            Severity::Warning,
+            "compound commands (if, for, while, until and case) are not supported and the line is not converted",
+        );
+
+        line.to_string()
+    } else if let Some(windows_command) = convert_block_line(line, state) {
+        windows_command
+    } else if let Some(windows_command) = convert_list(line, state) {
+        windows_command
+    } else if let Some(windows_command) = convert_set(line, state) {
+        windows_command
+    } else if let Some(windows_command) = convert_assignments(line, state) {
+        windows_command
+    } else if let Some(windows_command) = convert_env_prefix(line, state) {
+        windows_command
+    } else if let Some(windows_command) = convert_command(line, state) {
         windows_command
     } else {
         // assume first word is the command
@@ -148,157 +3088,49 @@ fn convert_line(line: &str) -> String {
             }
         };
 
-        arguments = arguments.trim().to_string();
-
-        let (
-            mut windows_command,
-            flags_mappings,
-            pre_arguments,
-            post_arguments,
-            modify_path_separator,
-        ) = match shell_command {
-            "cp" => {
-                // There is no good `cp` equivalent on windows. There are
-                // two tools we can rely on:
-                //
-                // - xcopy, which is great for directory to directory
-                //   copies.
-                // - copy, which is great for file to file/directory copies.
-                //
-                // We can select which one to use based on the presence of
-                // the -r flag.
-                let win_cmd = match Regex::new("(^|\\s)-[^ ]*[rR]") {
-                    Ok(regex_instance) => {
-                        if regex_instance.is_match(&arguments) {
-                            "xcopy".to_string()
-                        } else {
-                            "copy".to_string()
-                        }
-                    }
-                    Err(_) => "copy".to_string(),
-                };
+        // convert quoting, escape literal text and replace vars
+        let escape_context = state.escape_context();
+        let shell_command = convert_text(shell_command, &escape_context);
+        arguments = convert_text(arguments.trim(), &escape_context);
 
-                let flags_mappings = if win_cmd == "xcopy".to_string() {
-                    vec![("-[rR]", "/E")]
+        let (mut windows_command, pre_arguments, post_arguments) = match shell_command.as_str() {
+            "exit" | "return" => {
+                // exit /b only exits the batch script and not the calling cmd window
+                let post_arguments = if arguments.is_empty() {
+                    vec![" %ERRORLEVEL%".to_string()]
                 } else {
                     vec![]
                 };
-                (win_cmd, flags_mappings, vec![], vec![], true)
-            }
-            "mv" => ("move".to_string(), vec![], vec![], vec![], true),
-            "ls" => ("dir".to_string(), vec![], vec![], vec![], true),
-            "rm" => {
-                let win_cmd = match Regex::new("-[a-zA-Z]*[rR][a-zA-Z]* ") {
-                    Ok(regex_instance) => {
-                        if regex_instance.is_match(&arguments) {
-                            "rmdir".to_string()
-                        } else {
-                            "del".to_string()
-                        }
-                    }
-                    Err(_) => "del".to_string(),
-                };
-
-                let flags_mappings = if win_cmd == "rmdir".to_string() {
-                    vec![("-([rR][fF]|[fF][rR]) ", "/S /Q "), ("-[rR]+ ", "/S ")]
-                } else {
-                    vec![("-[fF] ", "/Q ")]
-                };
-
-                // If there is a -f flag, add " 2>nul" to the end of the command to suppress errors,
-                //   and add " || cd ." as a lightweight command that always returns a success exit code.
-                let post_arguments = match Regex::new(r#"(?i)^-\S*f\S*"#) {
-                    Ok(regex_instance) => {
-                        if regex_instance.is_match(&arguments) {
-                            vec![" 2>nul || cd .".to_owned()]
-                        } else {
-                            vec![]
-                        }
-                    }
-                    Err(_) => vec![],
-                };
-
-                (win_cmd, flags_mappings, vec![], post_arguments, true)
-            }
-            "mkdir" => (
-                "mkdir".to_string(),
-                vec![("-[pP]", "")],
-                vec![],
-                vec![],
-                true,
-            ),
-            "clear" => ("cls".to_string(), vec![], vec![], vec![], false),
-            "grep" => ("find".to_string(), vec![], vec![], vec![], false),
-            "pwd" => ("chdir".to_string(), vec![], vec![], vec![], false),
-            "export" => ("set".to_string(), vec![], vec![], vec![], false),
-            "unset" => (
-                "set".to_string(),
-                vec![],
-                vec![],
-                vec!["=".to_string()],
-                false,
-            ),
-            "touch" => {
-                let mut file_arg = arguments.replace("/", "\\").to_string();
-                file_arg.push_str("+,,");
 
-                (
-                    "copy".to_string(),
-                    vec![],
-                    vec!["/B ".to_string(), file_arg.clone()],
-                    vec![],
-                    true,
-                )
+                ("exit".to_string(), vec!["/b".to_string()], post_arguments)
             }
-            "set" => (
-                "@echo".to_string(),
-                vec![("-x", "on"), ("\\+x", "off")],
-                vec![],
-                vec![],
-                false,
-            ),
-            _ => (shell_command.to_string(), vec![], vec![], vec![], false),
+            "clear" => ("cls".to_string(), vec![], vec![]),
+            "pwd" => ("chdir".to_string(), vec![], vec![]),
+            "unset" => ("set".to_string(), vec![], vec!["=".to_string()]),
+            _ => (shell_command.to_string(), vec![], vec![]),
         };
 
         // modify paths
-        if modify_path_separator {
-            arguments = arguments.replace("/", "\\");
-        }
         windows_command = windows_command.replace("/", "\\");
 
         let mut windows_arguments = arguments.to_string();
 
         // add pre arguments
-        windows_arguments = if pre_arguments.len() > 0 {
+        windows_arguments = if !pre_arguments.is_empty() {
             add_arguments(&windows_arguments, pre_arguments, true)
         } else {
             windows_arguments
         };
 
-        // replace flags
-        windows_arguments = if flags_mappings.len() > 0 {
-            replace_flags(&arguments, flags_mappings)
-        } else {
-            windows_arguments
-        };
-
-        // replace vars
-        windows_arguments = if windows_arguments.len() > 0 {
-            replace_vars(&windows_arguments)
-        } else {
-            windows_arguments
-        };
-        windows_command = replace_vars(&windows_command);
-
         // add post arguments
-        windows_arguments = if post_arguments.len() > 0 {
+        windows_arguments = if !post_arguments.is_empty() {
             add_arguments(&windows_arguments, post_arguments, false)
         } else {
             windows_arguments
         };
 
-        if windows_arguments.len() > 0 {
-            windows_command.push_str(" ");
+        if !windows_arguments.is_empty() {
+            windows_command.push(' ');
             windows_command.push_str(&windows_arguments);
         }
 
@@ -308,22 +3140,97 @@ fn convert_line(line: &str) -> String {
 
 /// Converts the provided shell script and returns the windows batch script text.
 pub(crate) fn run(script: &str) -> String {
+    run_with_diagnostics(script).script
+}
+
+/// Converts the provided shell script and returns the windows batch script text
+/// together with the diagnostics found while converting it.
+pub(crate) fn run_with_diagnostics(script: &str) -> Conversion {
+    run_with_options(script, &Options::new())
+}
+
+/// Converts the provided shell script based on the provided options and returns the windows
+/// batch script text together with the diagnostics found while converting it.
+pub(crate) fn run_with_options(script: &str, options: &Options) -> Conversion {
     let lines: Vec<&str> = script.split('\n').collect();
     let mut windows_batch = vec![];
+    let mut state = State {
+        options: options.clone(),
+        track_oldpwd: lines.iter().any(|line| {
+            parser::split_list(line).iter().any(|(command, _)| {
+                let words = parser::split_words(command);
+                words.len() > 1 && words[0] == "cd" && words[1] == "-"
+            })
+        }),
+        ..State::default()
+    };
 
-    for mut line in lines {
+    let mut indexed_lines = lines.into_iter().enumerate();
+    while let Some((index, mut line)) = indexed_lines.next() {
+        state.line_number = index + 1;
         line = line.trim();
-        let mut line_string = line.to_string();
+        let line_string = line.to_string();
+
+        // the here document lines are the input of the command and are not converted
+        if let Some(heredoc_start) = parser::find_heredoc(line) {
+            let mut heredoc = Heredoc {
+                lines: vec![],
+                expand: heredoc_start.expand,
+            };
+            for (_, heredoc_line) in indexed_lines.by_ref() {
+                let heredoc_line = heredoc_line.trim_end_matches('\r');
+                if heredoc_line.trim() == heredoc_start.delimiter {
+                    break;
+                }
+
+                let heredoc_line = if heredoc_start.strip_tabs {
+                    heredoc_line.trim_start_matches('\t')
+                } else {
+                    heredoc_line
+                };
+                heredoc.lines.push(heredoc_line.to_string());
+            }
+            state.heredoc = Some(heredoc);
+        }
+
+        let error_check = state.errexit && is_command(line);
+        state.list_error_check = error_check;
+        state.conditional_command = error_check;
 
         // convert line
-        let converted_line = if line_string.len() == 0 {
+        let mut converted_line = if line_string.is_empty() {
             line_string
         } else {
-            convert_line(&mut line_string)
+            convert_line(&line_string, &mut state)
         };
+        state.conditional_command = false;
+
+        // emulate set -e by exiting with the error code of the failed command,
+        // the check is already added to each command of converted lists
+        if std::mem::take(&mut state.list_error_check) && !converted_line.is_empty() {
+            converted_line.push(' ');
+            converted_line.push_str(ERROR_EXIT);
+        }
+
+        if state.heredoc.take().is_some() {
+            state.add_diagnostic(
+                Severity::Warning,
+                "here documents are only supported as cat input and are ignored",
+            );
+        }
 
         windows_batch.push(converted_line);
     }
 
-    windows_batch.join("\n")
+    if state.options.propagate_exit_code {
+        match windows_batch.last_mut() {
+            Some(last_line) if last_line.is_empty() => last_line.push_str(EXIT_WITH_ERROR_CODE),
+            _ => windows_batch.push(EXIT_WITH_ERROR_CODE.to_string()),
+        }
+    }
+
+    Conversion {
+        script: windows_batch.join("\n"),
+        diagnostics: state.diagnostics,
+    }
 }
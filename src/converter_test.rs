@@ -72,41 +72,6 @@ fn add_arguments_post_empty_args_and_additional_values() {
     assert_eq!(value, "1 2 3");
 }
 
-#[test]
-fn replace_flags_all_empty() {
-    let value = replace_flags("", vec![]);
-
-    assert_eq!(value, "");
-}
-
-#[test]
-fn replace_flags_args_empty_replacment_existing() {
-    let value = replace_flags("", vec![("linux", "windows")]);
-
-    assert_eq!(value, "");
-}
-
-#[test]
-fn replace_flags_args_existing_replacment_empty() {
-    let value = replace_flags("linux", vec![]);
-
-    assert_eq!(value, "linux");
-}
-
-#[test]
-fn replace_flags_multiple() {
-    let value = replace_flags(
-        "linux1 LiNux2 somethingelse",
-        vec![
-            ("linux1", "windows1"),
-            ("[lL]i[nN]ux[1-9]", "windowsX"),
-            ("unknown", "bad"),
-        ],
-    );
-
-    assert_eq!(value, "windows1 windowsX somethingelse");
-}
-
 #[test]
 fn replace_full_vars_empty() {
     let value = replace_full_vars("");
@@ -280,7 +245,7 @@ fn run_comment() {
 fn run_command() {
     let output = run("cp file1 file2");
 
-    assert_eq!(output, "copy file1 file2");
+    assert_eq!(output, "copy \"file1\" \"file2\"");
 }
 
 #[test]
@@ -299,241 +264,2411 @@ fn run_multi_line() {
         r#"
 
 @REM this is some test code
-copy file1 file2
+copy "file1" "file2"
 
 @REM another
-move file2 file3
+move "file2" "file3"
+"#
+    );
+}
+
+#[test]
+fn run_errexit() {
+    let output = run(r#"
+        cp file1 file2
+        set -e
+        #copy
+        cp file1 file2
+        export A=1
+
+        set +e
+        mv file2 file3
+        "#);
+
+    assert_eq!(
+        output,
+        r#"
+copy "file1" "file2"
+
+@REM copy
+copy "file1" "file2" || exit /b
+set "A=1"
+
+
+move "file2" "file3"
 "#
     );
 }
 
+#[test]
+fn run_errexit_and_or_list() {
+    let output = run(r#"set -e
+        command -v cargo >/dev/null && cargo --version
+        make || make clean"#);
+
+    assert_eq!(
+        output,
+        "\nwhere cargo >nul 2>&1 && (cargo --version || exit /b)\nmake || (make clean || exit /b)"
+    );
+}
+
+#[test]
+fn run_errexit_tolerated_failure() {
+    let output = run(r#"set -e
+        grep -q foo file || true
+        echo found"#);
+
+    assert_eq!(
+        output,
+        "\nfind \"foo\" < \"file\" >nul || (cd . || exit /b)\necho found || exit /b"
+    );
+}
+
+#[test]
+fn run_errexit_list() {
+    let output = run(r#"set -e
+        cd sub; FOO=1 make | sort; (a && b)"#);
+
+    assert_eq!(
+        output,
+        r#"
+cd /d "sub" || exit /b & cmd /c "set "FOO=1" & make" | sort || exit /b & cmd /c "a && b" || exit /b"#
+    );
+}
+
+#[test]
+fn run_errexit_compound_command() {
+    let output = run("set -e\nif [ -f x ]; then echo y; fi");
+
+    assert_eq!(output, "\nif [ -f x ]; then echo y; fi");
+}
+
 #[test]
 fn convert_line_empty() {
-    let output = convert_line("");
+    let output = convert_line("", &mut State::default());
 
     assert_eq!(output, "");
 }
 
 #[test]
 fn convert_line_unhandled() {
-    let output = convert_line("newcommand path/arg1 path/arg2");
+    let output = convert_line("newcommand path/arg1 path/arg2", &mut State::default());
 
     assert_eq!(output, "newcommand path/arg1 path/arg2");
 }
 
 #[test]
 fn convert_line_with_hint() {
-    let output = convert_line("test 123 abc # shell2batch: windows 123 windows abc");
+    let output = convert_line(
+        "test 123 abc # shell2batch: windows 123 windows abc",
+        &mut State::default(),
+    );
 
     assert_eq!(output, "windows 123 windows abc");
 }
 
 #[test]
 fn convert_line_with_hint_trim() {
-    let output = convert_line("test 123 abc # shell2batch:    windows 123 windows abc   ");
+    let output = convert_line(
+        "test 123 abc # shell2batch:    windows 123 windows abc   ",
+        &mut State::default(),
+    );
 
     assert_eq!(output, "windows 123 windows abc");
 }
 
 #[test]
 fn convert_line_with_hint_empty() {
-    let output = convert_line("test 123 abc # shell2batch:");
+    let output = convert_line("test 123 abc # shell2batch:", &mut State::default());
 
     assert_eq!(output, "");
 }
 
 #[test]
 fn convert_line_with_hint_start_of_line() {
-    let output = convert_line("# shell2batch: windows 123 windows abc");
+    let output = convert_line(
+        "# shell2batch: windows 123 windows abc",
+        &mut State::default(),
+    );
 
     assert_eq!(output, "windows 123 windows abc");
 }
 
 #[test]
 fn convert_line_comment() {
-    let output = convert_line("#test/test");
+    let output = convert_line("#test/test", &mut State::default());
 
     assert_eq!(output, "@REM test/test");
 }
 
 #[test]
 fn convert_line_cp() {
-    let output = convert_line("cp dir/file1 dir/file2");
+    let output = convert_line("cp dir/file1 dir/file2", &mut State::default());
 
-    assert_eq!(output, "copy dir\\file1 dir\\file2");
+    assert_eq!(output, r#"copy "dir\file1" "dir\file2""#);
 }
 
 #[test]
 fn convert_line_cp_recursive() {
-    let output = convert_line("cp -r directory/sub1 director/sub2");
+    let output = convert_line("cp -r directory/sub1 director/sub2", &mut State::default());
 
-    assert_eq!(output, "xcopy /E directory\\sub1 director\\sub2");
+    assert_eq!(
+        output,
+        r#"xcopy /E /I /Y "directory\sub1" "director\sub2\""#
+    );
 }
 
 #[test]
 fn convert_line_cp_file_with_dash() {
-    let output = convert_line("cp file-r directory");
+    let output = convert_line("cp file-r directory", &mut State::default());
+
+    assert_eq!(output, r#"copy "file-r" "directory""#);
+}
+
+#[test]
+fn convert_line_cp_force() {
+    let output = convert_line("cp -f a.txt b.txt", &mut State::default());
+
+    assert_eq!(output, r#"copy /Y "a.txt" "b.txt""#);
+}
+
+#[test]
+fn convert_line_cp_interactive() {
+    let output = convert_line("cp -i a.txt b.txt", &mut State::default());
+
+    assert_eq!(output, r#"copy /-Y "a.txt" "b.txt""#);
+}
+
+#[test]
+fn convert_line_cp_no_clobber() {
+    let output = convert_line("cp -n a.txt b.txt", &mut State::default());
+
+    assert_eq!(output, r#"if not exist "b.txt" copy "a.txt" "b.txt""#);
+}
+
+#[test]
+fn convert_line_cp_multiple_sources() {
+    let output = convert_line("cp -n a.txt dir/b.txt out > log", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if not exist "out\a.txt" copy "a.txt" "out\" & if not exist "out\b.txt" copy "dir\b.txt" "out\") >"log""#
+    );
+}
+
+#[test]
+fn convert_line_cp_target_directory() {
+    let output = convert_line("cp --target-directory=out a.txt", &mut State::default());
+
+    assert_eq!(output, r#"copy "a.txt" "out\""#);
+}
+
+#[test]
+fn convert_line_cp_recursive_into_directory() {
+    let output = convert_line("cp -R src/app dist/", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "src\app\*" (xcopy /E /I /Y "src\app" "dist\app\") else xcopy /E /I /Y "src\app" "dist\")"#
+    );
+}
+
+#[test]
+fn convert_line_cp_recursive_content() {
+    let output = convert_line("cp -r src/. dist/", &mut State::default());
+
+    assert_eq!(output, r#"xcopy /E /I /Y "src" "dist\""#);
+}
+
+#[test]
+fn convert_line_cp_archive_verbose() {
+    let output = convert_line("cp -av src/ dist", &mut State::default());
+
+    assert_eq!(output, r#"xcopy /E /I /Y /K /H /F "src" "dist\""#);
+}
+
+#[test]
+fn convert_line_cp_recursive_no_clobber() {
+    let mut state = State::default();
+    let output = convert_line("cp -rn src dist", &mut state);
+
+    assert_eq!(output, r#"xcopy /E /I /Y "src" "dist\""#);
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_cp_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("cp -u a b", &mut state);
+
+    assert_eq!(output, r#"copy "a" "b""#);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "cp option -u is not supported and is ignored"
+    );
+}
+
+#[test]
+fn convert_line_cp_no_target() {
+    let mut state = State::default();
+    let output = convert_line("cp a", &mut state);
+
+    assert_eq!(output, r#"copy "a""#);
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+fn robocopy_state() -> State {
+    let mut state = State::default();
+    state.options.use_robocopy = true;
+    state
+}
+
+#[test]
+fn convert_line_cp_recursive_robocopy() {
+    let output = convert_line("cp -r src/app dist", &mut robocopy_state());
 
-    assert_eq!(output, "copy file-r directory");
+    assert_eq!(
+        output,
+        r#"(if exist "src\app\*" (robocopy "src\app" "dist" /E /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else copy "src\app" "dist")"#
+    );
+}
+
+#[test]
+fn convert_line_cp_archive_file_robocopy() {
+    let output = convert_line("cp -a config.toml backup.toml", &mut robocopy_state());
+
+    assert_eq!(
+        output,
+        r#"(if exist "config.toml\*" (robocopy "config.toml" "backup.toml" /E /DCOPY:T /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else copy "config.toml" "backup.toml")"#
+    );
+}
+
+#[test]
+fn convert_line_cp_recursive_content_robocopy() {
+    let output = convert_line("cp -r src/. dist", &mut robocopy_state());
+
+    assert_eq!(
+        output,
+        r#"(robocopy "src" "dist" /E /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call ))"#
+    );
+}
+
+#[test]
+fn convert_line_cp_recursive_robocopy_into_directory() {
+    let output = convert_line("cp -rnv src/app dist/", &mut robocopy_state());
+
+    assert_eq!(
+        output,
+        r#"(if exist "src\app\*" (robocopy "src\app" "dist\app" /E /XC /XN /XO /V & if errorlevel 8 (cmd /c exit 1) else (call )) else copy "src\app" "dist\")"#
+    );
+}
+
+#[test]
+fn convert_line_cp_file_robocopy() {
+    let output = convert_line("cp a.txt b.txt", &mut robocopy_state());
+
+    assert_eq!(output, r#"copy "a.txt" "b.txt""#);
+}
+
+#[test]
+fn convert_line_mv_robocopy() {
+    let output = convert_line("mv build /d/out", &mut robocopy_state());
+
+    assert_eq!(
+        output,
+        r#"(if exist "build\*" (robocopy "build" "d:\out" /E /MOVE /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else move "build" "d:\out")"#
+    );
+}
+
+#[test]
+fn convert_line_mv_robocopy_into_directory() {
+    let output = convert_line("mv a b out", &mut robocopy_state());
+
+    assert_eq!(
+        output,
+        r#"((if exist "a\*" (robocopy "a" "out\a" /E /MOVE /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else move "a" "out\") & (if exist "b\*" (robocopy "b" "out\b" /E /MOVE /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else move "b" "out\"))"#
+    );
+}
+
+#[test]
+fn convert_line_rsync_content() {
+    let output = convert_line(
+        "rsync -avz --delete --exclude=.git src/ backup",
+        &mut State::default(),
+    );
+
+    assert_eq!(
+        output,
+        r#"(robocopy "src" "backup" /E /PURGE /XF ".git" /XD ".git" /V & if errorlevel 8 (cmd /c exit 1) else (call ))"#
+    );
+}
+
+#[test]
+fn convert_line_rsync_directory() {
+    let output = convert_line("rsync -a --dry-run src backup/", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "src\*" (robocopy "src" "backup\src" /E /L /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else copy "src" "backup")"#
+    );
+}
+
+#[test]
+fn convert_line_rsync_remote() {
+    let mut state = State::default();
+    convert_line("rsync -a dist/ user@host:/var/www", &mut state);
+
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "rsync to remote hosts is not supported"
+    );
+}
+
+#[test]
+fn run_with_options_robocopy_errexit() {
+    let mut options = Options::new();
+    options.use_robocopy = true;
+
+    let conversion = run_with_options("set -e\ncp -r a b", &options);
+
+    assert_eq!(
+        conversion.script,
+        "\n(if exist \"a\\*\" (robocopy \"a\" \"b\" /E /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else copy \"a\" \"b\") || exit /b"
+    );
 }
 
 #[test]
 fn convert_line_mv() {
-    let output = convert_line("mv dir/file1 dir/file2");
+    let output = convert_line("mv dir/file1 dir/file2", &mut State::default());
 
-    assert_eq!(output, "move dir\\file1 dir\\file2");
+    assert_eq!(output, r#"move "dir\file1" "dir\file2""#);
 }
 
 #[test]
-fn convert_line_ls() {
-    let output = convert_line("ls");
+fn convert_line_mv_force() {
+    let output = convert_line("mv -f a.txt b.txt", &mut State::default());
 
-    assert_eq!(output, "dir");
+    assert_eq!(output, r#"move /Y "a.txt" "b.txt""#);
 }
 
 #[test]
-fn convert_line_rm() {
-    let output = convert_line("rm dir/file");
+fn convert_line_mv_interactive() {
+    let output = convert_line("mv -fi a.txt b.txt", &mut State::default());
 
-    assert_eq!(output, "del dir\\file");
+    assert_eq!(output, r#"move /-Y "a.txt" "b.txt""#);
 }
 
 #[test]
-fn convert_line_rm_no_prompt() {
-    let output = convert_line("rm -f dir/file");
+fn convert_line_mv_no_clobber() {
+    let output = convert_line("mv -n a.txt b.txt", &mut State::default());
 
-    assert_eq!(output, "del /Q dir\\file 2>nul || cd .");
+    assert_eq!(output, r#"if not exist "b.txt" move "a.txt" "b.txt""#);
 }
 
 #[test]
-fn convert_line_rm_with_minus_r_in_path() {
-    let output = convert_line("rm ./dir-dir/.file");
+fn convert_line_mv_multiple_sources() {
+    let output = convert_line("mv -v a.txt dir/b.txt out", &mut State::default());
 
-    assert_eq!(output, "del .\\dir-dir\\.file");
+    assert_eq!(output, r#"(move "a.txt" "out\" & move "dir\b.txt" "out\")"#);
 }
 
 #[test]
-fn convert_line_rm_recursive() {
-    let output = convert_line("rm -r dir/file");
+fn convert_line_mv_target_directory() {
+    let output = convert_line("mv -n -t out/ a.txt", &mut State::default());
 
-    assert_eq!(output, "rmdir /S dir\\file");
+    assert_eq!(output, r#"if not exist "out\a.txt" move "a.txt" "out\""#);
 }
 
 #[test]
-fn convert_line_rm_no_prompt_and_recursive_v1() {
-    let output = convert_line("rm -rf dir/file");
+fn convert_line_mv_between_drives() {
+    let mut state = State::default();
+    let output = convert_line("mv build /d/out", &mut state);
 
-    assert_eq!(output, "rmdir /S /Q dir\\file 2>nul || cd .");
+    assert_eq!(output, r#"move "build" "d:\out""#);
+    assert_eq!(state.diagnostics.len(), 1);
 }
 
 #[test]
-fn convert_line_rm_no_prompt_and_recursive_v2() {
-    let output = convert_line("rm -fr dir/file");
+fn convert_line_mv_no_target() {
+    let mut state = State::default();
+    let output = convert_line("mv a", &mut state);
 
-    assert_eq!(output, "rmdir /S /Q dir\\file 2>nul || cd .");
+    assert_eq!(output, "move");
+    assert_eq!(state.diagnostics.len(), 1);
 }
 
 #[test]
-fn convert_line_rm_no_prompt_and_recursive_v3() {
-    let output = convert_line("rm -Rf dir/file");
+fn convert_line_mv_robocopy_force() {
+    let output = convert_line("mv -f a b", &mut robocopy_state());
 
-    assert_eq!(output, "rmdir /S /Q dir\\file 2>nul || cd .");
+    assert_eq!(
+        output,
+        r#"(if exist "a\*" (robocopy "a" "b" /E /MOVE /NFL /NDL /NJH /NJS /NP & if errorlevel 8 (cmd /c exit 1) else (call )) else move /Y "a" "b")"#
+    );
 }
 
 #[test]
-fn convert_line_rm_no_prompt_and_recursive_v4() {
-    let output = convert_line("rm -fR dir/file");
+fn convert_line_ln_symbolic_file() {
+    let mut state = State::default();
+    let output = convert_line("ln -s ../shared/config.toml config.toml", &mut state);
 
-    assert_eq!(output, "rmdir /S /Q dir\\file 2>nul || cd .");
+    assert_eq!(
+        output,
+        r#"mklink "config.toml" "..\shared\config.toml" >nul"#
+    );
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Info);
 }
 
 #[test]
-fn convert_line_rm_no_prompt_and_recursive_multiple_files() {
-    let output = convert_line("rm -rf dir/file1 dir/file2");
+fn convert_line_ln_symbolic_directory() {
+    let output = convert_line("ln -s /c/tools/ bin/tools", &mut State::default());
 
-    assert_eq!(output, "rmdir /S /Q dir\\file1 dir\\file2 2>nul || cd .");
+    assert_eq!(output, r#"mklink /D "bin\tools" "c:\tools" >nul"#);
 }
 
 #[test]
-fn convert_line_mkdir() {
-    let output = convert_line("mkdir dir1/dir2");
+fn convert_line_ln_symbolic_runtime_check() {
+    let output = convert_line("ln -s ../shared config/shared", &mut State::default());
 
-    assert_eq!(output, "mkdir dir1\\dir2");
+    assert_eq!(
+        output,
+        r#"(if exist "config\..\shared\*" (mklink /D "config\shared" "..\shared" >nul) else (mklink "config\shared" "..\shared" >nul))"#
+    );
 }
 
 #[test]
-fn convert_line_mkdir_and_parents() {
-    let output = convert_line("mkdir -p dir1/dir2");
+fn convert_line_ln_symbolic_absolute_target() {
+    let output = convert_line("ln -s $HOME/.cargo cargo", &mut State::default());
 
-    assert_eq!(output, "mkdir  dir1\\dir2");
+    assert_eq!(
+        output,
+        r#"(if exist "%HOME%\.cargo\*" (mklink /D "cargo" "%HOME%\.cargo" >nul) else (mklink "cargo" "%HOME%\.cargo" >nul))"#
+    );
 }
 
 #[test]
-fn convert_line_clear() {
-    let output = convert_line("clear");
+fn convert_line_ln_hard() {
+    let mut state = State::default();
+    let output = convert_line("ln data.bin data-link.bin", &mut state);
 
-    assert_eq!(output, "cls");
+    assert_eq!(output, r#"mklink /H "data-link.bin" "data.bin" >nul"#);
+    assert!(state.diagnostics.is_empty());
 }
 
 #[test]
-fn convert_line_grep() {
-    let output = convert_line("grep");
+fn convert_line_ln_force() {
+    let output = convert_line("ln -sf app.conf /etc/app.conf", &mut State::default());
 
-    assert_eq!(output, "find");
+    assert_eq!(
+        output,
+        r#"((if exist "\etc\app.conf\*" (rmdir "\etc\app.conf") else if exist "\etc\app.conf" (del /F /Q "\etc\app.conf")) & mklink "\etc\app.conf" "app.conf" >nul)"#
+    );
 }
 
 #[test]
-fn convert_line_pwd() {
-    let output = convert_line("pwd");
+fn convert_line_ln_link_directory() {
+    let output = convert_line("ln -s a.txt b.txt links/", &mut State::default());
 
-    assert_eq!(output, "chdir");
+    assert_eq!(
+        output,
+        r#"(mklink "links\a.txt" "a.txt" >nul & mklink "links\b.txt" "b.txt" >nul)"#
+    );
 }
 
 #[test]
-fn convert_line_export() {
-    let output = convert_line("export A=B");
+fn convert_line_ln_no_link_name() {
+    let output = convert_line("ln -s /opt/tool.exe", &mut State::default());
 
-    assert_eq!(output, "set A=B");
+    assert_eq!(output, r#"mklink "tool.exe" "\opt\tool.exe" >nul"#);
 }
 
 #[test]
-fn convert_line_unset() {
-    let output = convert_line("unset A");
+fn convert_line_which() {
+    let output = convert_line("which cargo rustc", &mut State::default());
 
-    assert_eq!(output, "set A=");
+    assert_eq!(output, "where cargo rustc");
 }
 
 #[test]
-fn convert_line_touch() {
-    let output = convert_line("touch ./dir/myfile.txt");
+fn convert_line_which_silent() {
+    let output = convert_line("which -s cargo", &mut State::default());
 
-    assert_eq!(output, "copy /B .\\dir\\myfile.txt+,, .\\dir\\myfile.txt");
+    assert_eq!(output, "where cargo >nul 2>&1");
 }
 
 #[test]
-fn convert_line_set_minus_x() {
-    let output = convert_line("set -x");
+fn convert_line_command_v_discarded_output() {
+    let output = convert_line(
+        "command -v cargo >/dev/null || exit 1",
+        &mut State::default(),
+    );
 
-    assert_eq!(output, "@echo on");
+    assert_eq!(output, "where cargo >nul 2>&1 || exit /b 1");
 }
 
 #[test]
-fn convert_line_set_plus_x() {
-    let output = convert_line("set +x");
+fn convert_line_command_v_discarded_all_output() {
+    let output = convert_line("command -v cargo &>/dev/null", &mut State::default());
 
-    assert_eq!(output, "@echo off");
+    assert_eq!(output, "where cargo >nul 2>&1");
 }
 
 #[test]
-fn convert_line_var_as_command() {
-    let output = convert_line("$MYVAR");
+fn convert_line_command_run() {
+    let output = convert_line("command cp a b", &mut State::default());
 
-    assert_eq!(output, "%MYVAR%");
+    assert_eq!(output, r#"copy "a" "b""#);
 }
 
 #[test]
-fn convert_line_var_as_part_of_command() {
-    let output = convert_line("./${MYVAR}.exe/something");
+fn convert_line_type() {
+    let output = convert_line("type -P git > git-path.txt", &mut State::default());
 
-    assert_eq!(output, ".\\%MYVAR%.exe\\something");
+    assert_eq!(output, r#"where git >"git-path.txt""#);
+}
+
+#[test]
+fn convert_line_hash() {
+    let output = convert_line("hash make 2>/dev/null && make", &mut State::default());
+
+    assert_eq!(output, "where make >nul 2>nul && make");
+}
+
+#[test]
+fn convert_line_which_no_name() {
+    let mut state = State::default();
+    let output = convert_line("which", &mut state);
+
+    assert_eq!(output, "where");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_sleep() {
+    let output = convert_line("sleep 5", &mut State::default());
+
+    assert_eq!(output, "timeout /t 5 /nobreak >nul");
+}
+
+#[test]
+fn convert_line_sleep_units() {
+    let output = convert_line("sleep 1m 30s", &mut State::default());
+
+    assert_eq!(output, "timeout /t 90 /nobreak >nul");
+}
+
+#[test]
+fn convert_line_sleep_fraction() {
+    let mut state = State::default();
+    let output = convert_line("sleep 0.5", &mut state);
+
+    assert_eq!(output, "timeout /t 1 /nobreak >nul");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_sleep_variable() {
+    let output = convert_line("sleep $DELAY", &mut State::default());
+
+    assert_eq!(output, "timeout /t %DELAY% /nobreak >nul");
+}
+
+#[test]
+fn convert_line_true() {
+    let output = convert_line("rm -f out.txt || true", &mut State::default());
+
+    assert_eq!(output, r#"del /F /Q "out.txt" 2>nul || cd . || cd ."#);
+}
+
+#[test]
+fn convert_line_colon() {
+    let output = convert_line(":", &mut State::default());
+
+    assert_eq!(output, "cd .");
+}
+
+#[test]
+fn convert_line_false() {
+    let output = convert_line("false", &mut State::default());
+
+    assert_eq!(output, "cmd /c exit 1");
+}
+
+#[test]
+fn convert_line_chmod_execute() {
+    let mut state = State::default();
+    let output = convert_line("chmod +x ./scripts/build.sh", &mut state);
+
+    assert_eq!(output, "");
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Info);
+}
+
+#[test]
+fn convert_line_chmod_execute_in_list() {
+    let output = convert_line("chmod 755 run.sh && ./run.sh", &mut State::default());
+
+    assert_eq!(output, r#"cd . && .\run.sh"#);
+}
+
+#[test]
+fn convert_line_chmod_read_only() {
+    let output = convert_line("chmod -w a.txt b.txt", &mut State::default());
+
+    assert_eq!(output, r#"(attrib +R "a.txt" & attrib +R "b.txt")"#);
+}
+
+#[test]
+fn convert_line_chmod_writable() {
+    let output = convert_line("chmod u+w,go-w config.toml", &mut State::default());
+
+    assert_eq!(output, r#"attrib -R "config.toml""#);
+}
+
+#[test]
+fn convert_line_chmod_other_permissions() {
+    let mut state = State::default();
+    let output = convert_line("chmod -v a=r out/lib.rs", &mut state);
+
+    assert_eq!(output, r#"attrib +R "out\lib.rs""#);
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Info);
+}
+
+#[test]
+fn convert_line_chown() {
+    let mut state = State::default();
+    let output = convert_line("chown -R user:group target", &mut state);
+
+    assert_eq!(output, "");
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Info);
+}
+
+#[test]
+fn convert_line_sudo() {
+    let mut state = State::default();
+    let output = convert_line("sudo -E -u root mkdir /opt/tools", &mut state);
+
+    assert_eq!(output, r#"mkdir "\opt\tools""#);
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Warning);
+}
+
+#[test]
+fn convert_line_sudo_in_list() {
+    let output = convert_line(
+        "sudo --user=root cp a b && sudo -- rm -f c",
+        &mut State::default(),
+    );
+
+    assert_eq!(output, r#"copy "a" "b" && del /F /Q "c" 2>nul || cd ."#);
+}
+
+#[test]
+fn convert_line_ls() {
+    let output = convert_line("ls", &mut State::default());
+
+    assert_eq!(output, "dir");
+}
+
+#[test]
+fn convert_line_ls_long_all() {
+    let output = convert_line("ls -lah ~/work", &mut State::default());
+
+    assert_eq!(output, r#"dir /a "%USERPROFILE%\work""#);
+}
+
+#[test]
+fn convert_line_ls_bare_recursive() {
+    let output = convert_line("ls -1R src > files.txt", &mut State::default());
+
+    assert_eq!(output, r#"dir /b /s "src" >"files.txt""#);
+}
+
+#[test]
+fn convert_line_ls_sort() {
+    let output = convert_line("ls -lt", &mut State::default());
+
+    assert_eq!(output, "dir /o-d");
+}
+
+#[test]
+fn convert_line_ls_reverse_sort() {
+    let output = convert_line("ls -S -r a b", &mut State::default());
+
+    assert_eq!(output, r#"dir /os "a" "b""#);
+}
+
+#[test]
+fn convert_line_ls_reverse_name() {
+    let output = convert_line("ls --reverse", &mut State::default());
+
+    assert_eq!(output, "dir /o-n");
+}
+
+#[test]
+fn convert_line_ls_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("ls -d --color=auto dir", &mut state);
+
+    assert_eq!(output, r#"dir "dir""#);
+    assert_eq!(state.diagnostics.len(), 2);
+    assert_eq!(
+        state.diagnostics[1].message,
+        "ls option --color=auto is not supported and is ignored"
+    );
+}
+
+#[test]
+fn convert_line_rm() {
+    let output = convert_line("rm dir/file", &mut State::default());
+
+    assert_eq!(output, r#"del "dir\file""#);
+}
+
+#[test]
+fn convert_line_rm_recursive_no_files() {
+    let mut state = State::default();
+    let output = convert_line("rm -rf", &mut state);
+
+    assert_eq!(output, "cd . 2>nul || cd .");
+    assert!(state.diagnostics.is_empty());
+}
+
+#[test]
+fn convert_line_rm_force() {
+    let output = convert_line("rm -f dir/file", &mut State::default());
+
+    assert_eq!(output, r#"del /F /Q "dir\file" 2>nul || cd ."#);
+}
+
+#[test]
+fn convert_line_rm_hidden_file() {
+    let output = convert_line("rm ./dir-dir/.file", &mut State::default());
+
+    assert_eq!(output, r#"del ".\dir-dir\.file""#);
+}
+
+#[test]
+fn convert_line_rm_multiple_files() {
+    let output = convert_line("rm -- a.txt -b.txt", &mut State::default());
+
+    assert_eq!(output, r#"del "a.txt" "-b.txt""#);
+}
+
+#[test]
+fn convert_line_rm_recursive() {
+    let output = convert_line("rm -r dir/file", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "dir\file\*" (rmdir /S /Q "dir\file") else (del "dir\file"))"#
+    );
+}
+
+#[test]
+fn convert_line_rm_recursive_force() {
+    let output = convert_line("rm -rf dir/file", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "dir\file\*" (rmdir /S /Q "dir\file") else if exist "dir\file" (del /F /Q "dir\file")) 2>nul || cd ."#
+    );
+}
+
+#[test]
+fn convert_line_rm_force_recursive() {
+    let output = convert_line("rm -fr dir/", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "dir\*" (rmdir /S /Q "dir") else if exist "dir" (del /F /Q "dir")) 2>nul || cd ."#
+    );
+}
+
+#[test]
+fn convert_line_rm_separate_flags() {
+    let output = convert_line("rm -R -f dir", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "dir\*" (rmdir /S /Q "dir") else if exist "dir" (del /F /Q "dir")) 2>nul || cd ."#
+    );
+}
+
+#[test]
+fn convert_line_rm_long_options() {
+    let output = convert_line("rm --recursive --force $DIR", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "%DIR%\*" (rmdir /S /Q "%DIR%") else if exist "%DIR%" (del /F /Q "%DIR%")) 2>nul || cd ."#
+    );
+}
+
+#[test]
+fn convert_line_rm_recursive_multiple() {
+    let output = convert_line("rm -rf dir/file1 dir/file2", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"((if exist "dir\file1\*" (rmdir /S /Q "dir\file1") else if exist "dir\file1" (del /F /Q "dir\file1")) & (if exist "dir\file2\*" (rmdir /S /Q "dir\file2") else if exist "dir\file2" (del /F /Q "dir\file2"))) 2>nul || cd ."#
+    );
+}
+
+#[test]
+fn convert_line_rm_empty_directory() {
+    let output = convert_line("rm -d dir", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist "dir\*" (rmdir "dir") else (del "dir"))"#
+    );
+}
+
+#[test]
+fn convert_line_rm_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("rm -i file", &mut state);
+
+    assert_eq!(output, r#"del "file""#);
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_mkdir() {
+    let output = convert_line("mkdir dir1/dir2", &mut State::default());
+
+    assert_eq!(output, r#"mkdir "dir1\dir2""#);
+}
+
+#[test]
+fn convert_line_mkdir_and_parents() {
+    let output = convert_line("mkdir -p dir1/dir2", &mut State::default());
+
+    assert_eq!(output, r#"if not exist "dir1\dir2\" mkdir "dir1\dir2""#);
+}
+
+#[test]
+fn convert_line_mkdir_multiple() {
+    let output = convert_line("mkdir a b/c", &mut State::default());
+
+    assert_eq!(output, r#"mkdir "a" "b\c""#);
+}
+
+#[test]
+fn convert_line_mkdir_and_parents_multiple() {
+    let output = convert_line(
+        "mkdir --parents target/ \"$OUT/logs\"",
+        &mut State::default(),
+    );
+
+    assert_eq!(
+        output,
+        r#"(if not exist "target\" mkdir "target" & if not exist "%OUT%\logs\" mkdir "%OUT%\logs")"#
+    );
+}
+
+#[test]
+fn convert_line_mkdir_mode() {
+    let mut state = State::default();
+    let output = convert_line("mkdir -pm 700 secrets", &mut state);
+
+    assert_eq!(output, r#"if not exist "secrets\" mkdir "secrets""#);
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Info);
+}
+
+#[test]
+fn convert_line_mkdir_and_parents_no_directories() {
+    let output = convert_line("mkdir -p", &mut State::default());
+
+    assert_eq!(output, "cd .");
+}
+
+#[test]
+fn convert_line_exit() {
+    let output = convert_line("exit", &mut State::default());
+
+    assert_eq!(output, "exit /b %ERRORLEVEL%");
+}
+
+#[test]
+fn convert_line_exit_with_code() {
+    let output = convert_line("exit 2", &mut State::default());
+
+    assert_eq!(output, "exit /b 2");
+}
+
+#[test]
+fn convert_line_exit_with_last_code() {
+    let output = convert_line("exit $?", &mut State::default());
+
+    assert_eq!(output, "exit /b %ERRORLEVEL%");
+}
+
+#[test]
+fn convert_line_return() {
+    let output = convert_line("return 1", &mut State::default());
+
+    assert_eq!(output, "exit /b 1");
+}
+
+#[test]
+fn convert_line_cd() {
+    let output = convert_line("cd ../target", &mut State::default());
+
+    assert_eq!(output, "cd /d \"..\\target\"");
+}
+
+#[test]
+fn convert_line_cd_drive() {
+    let output = convert_line("cd /d/work", &mut State::default());
+
+    assert_eq!(output, "cd /d \"d:\\work\"");
+}
+
+#[test]
+fn convert_line_cd_drive_root() {
+    let output = convert_line("cd /c", &mut State::default());
+
+    assert_eq!(output, "cd /d \"c:\\\"");
+}
+
+#[test]
+fn convert_line_cd_not_drive() {
+    let output = convert_line("cd /tmp/dir", &mut State::default());
+
+    assert_eq!(output, "cd /d \"\\tmp\\dir\"");
+}
+
+#[test]
+fn convert_line_cd_home() {
+    let mut output = convert_line("cd", &mut State::default());
+    assert_eq!(output, "cd /d \"%USERPROFILE%\"");
+
+    output = convert_line("cd ~", &mut State::default());
+    assert_eq!(output, "cd /d \"%USERPROFILE%\"");
+
+    output = convert_line("cd ~/projects", &mut State::default());
+    assert_eq!(output, "cd /d \"%USERPROFILE%\\projects\"");
+}
+
+#[test]
+fn convert_line_cd_quoted_with_vars() {
+    let output = convert_line(r#"cd "$HOME/my dir""#, &mut State::default());
+
+    assert_eq!(output, "cd /d \"%HOME%\\my dir\"");
+}
+
+#[test]
+fn convert_line_cd_with_flags() {
+    let output = convert_line("cd -P dir", &mut State::default());
+
+    assert_eq!(output, "cd /d \"dir\"");
+}
+
+#[test]
+fn convert_line_cd_redirection() {
+    let output = convert_line("cd dir >/dev/null 2>&1", &mut State::default());
+
+    assert_eq!(output, "cd /d \"dir\" >nul 2>&1");
+}
+
+#[test]
+fn run_cd_previous_directory() {
+    let output = run("cd dir1\ncd dir2\ncd -");
+
+    assert_eq!(
+        output,
+        r#"set "OLDPWD=%CD%" & cd /d "dir1"
+set "OLDPWD=%CD%" & cd /d "dir2"
+set "OLDPWD=%CD%" & cd /d "%OLDPWD%""#
+    );
+}
+
+#[test]
+fn run_cd_previous_directory_in_list() {
+    let output = run("cd build && make && cd -");
+
+    assert_eq!(
+        output,
+        r#"(call set "OLDPWD=%%CD%%" & cd /d "build") && make && (call set "SHELL2BATCH_OLDPWD=%%OLDPWD%%" & call set "OLDPWD=%%CD%%" & call cd /d "%%SHELL2BATCH_OLDPWD%%")"#
+    );
+}
+
+#[test]
+fn convert_line_pushd() {
+    let output = convert_line("pushd /c/work/dir > /dev/null", &mut State::default());
+
+    assert_eq!(output, "pushd \"c:\\work\\dir\" >nul");
+}
+
+#[test]
+fn convert_line_pushd_no_directory() {
+    let mut state = State::default();
+    let output = convert_line("pushd", &mut state);
+
+    assert_eq!(output, "pushd");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_popd() {
+    let output = convert_line("popd &>/dev/null", &mut State::default());
+
+    assert_eq!(output, "popd >nul 2>&1");
+}
+
+#[test]
+fn convert_path_value_relative() {
+    let value = convert_path_value("./a/b", &State::default());
+
+    assert_eq!(value, ".\\a\\b");
+}
+
+#[test]
+fn convert_path_value_escaped() {
+    let value = convert_path_value("'50%/a b'", &State::default());
+
+    assert_eq!(value, "50%%\\a b");
+}
+
+#[test]
+fn split_redirections_mixed() {
+    let arguments = parser::split_words("a > out/file b 2>>err &> /dev/null");
+    let (operands, redirections) = split_redirections(&arguments, &mut State::default());
+
+    assert_eq!(operands, vec!["a", "b"]);
+    assert_eq!(
+        redirections,
+        vec![">\"out\\file\"", "2>>\"err\"", ">nul 2>&1"]
+    );
+}
+
+#[test]
+fn convert_line_list() {
+    let output = convert_line(
+        "cp a/b c && rm -f d || mv e f; ls | grep x",
+        &mut State::default(),
+    );
+
+    assert_eq!(
+        output,
+        "copy \"a\\b\" \"c\" && del /F /Q \"d\" 2>nul || cd . || move \"e\" \"f\" & dir | find \"x\""
+    );
+}
+
+#[test]
+fn convert_line_list_trailing_separator() {
+    let output = convert_line("cp a b;", &mut State::default());
+
+    assert_eq!(output, "copy \"a\" \"b\"");
+}
+
+#[test]
+fn convert_line_list_no_op_command() {
+    let output = convert_line("export A && cp a b", &mut State::default());
+
+    assert_eq!(output, "cd . && copy \"a\" \"b\"");
+}
+
+#[test]
+fn convert_line_list_multiple_assignments() {
+    let output = convert_line("export A=1 B=2; cp a b", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\" & set \"B=2\" & copy \"a\" \"b\"");
+}
+
+#[test]
+fn convert_line_list_background() {
+    let mut state = State::default();
+    let output = convert_line("server & cp a b", &mut state);
+
+    assert_eq!(output, "server & copy \"a\" \"b\"");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_subshell() {
+    let output = convert_line("(cd sub && make)", &mut State::default());
+
+    assert_eq!(
+        output,
+        "setlocal & pushd . & (cd /d \"sub\" && make) & popd & endlocal"
+    );
+}
+
+#[test]
+fn convert_line_subshell_with_redirection() {
+    let output = convert_line("(cd sub; make) > out.txt; echo done", &mut State::default());
+
+    assert_eq!(
+        output,
+        "setlocal & pushd . & (cd /d \"sub\" & make) >\"out.txt\" & popd & endlocal & echo done"
+    );
+}
+
+#[test]
+fn convert_line_subshell_exit_code_used() {
+    let output = convert_line("(cd sub && make) || exit 1", &mut State::default());
+
+    assert_eq!(output, "cmd /c \"cd /d \"sub\" && make\" || exit /b 1");
+}
+
+#[test]
+fn convert_line_subshell_piped() {
+    let output = convert_line("(cat a; cat b) | grep x", &mut State::default());
+
+    assert_eq!(output, "(type \"a\" & type \"b\") | find \"x\"");
+}
+
+#[test]
+fn convert_line_brace_group() {
+    let output = convert_line("{ cp a b; mv c d; } 2>/dev/null", &mut State::default());
+
+    assert_eq!(output, "(copy \"a\" \"b\" & move \"c\" \"d\") 2>nul");
+}
+
+#[test]
+fn convert_line_brace_group_escape() {
+    let output = convert_line(r#"{ echo a\) 'b)'; }"#, &mut State::default());
+
+    assert_eq!(output, "(echo a^) b^))");
+}
+
+#[test]
+fn run_multi_line_subshell() {
+    let output = run(r#"(
+        cd sub
+        make
+        ) > out.txt"#);
+
+    assert_eq!(
+        output,
+        r#"setlocal & pushd . & (
+cd /d "sub"
+make
+) >"out.txt" & popd & endlocal"#
+    );
+}
+
+#[test]
+fn run_multi_line_brace_group() {
+    let output = run(r#"{
+        echo a\)
+        }"#);
+
+    assert_eq!(output, "(\necho a^)\n)");
+}
+
+#[test]
+fn run_multi_line_group_errexit() {
+    let output = run(r#"set -e
+        {
+        make
+        }"#);
+
+    assert_eq!(output, "\n(\nmake || exit /b\n)");
+}
+
+#[test]
+fn convert_line_compound_if() {
+    let mut state = State::default();
+    let output = convert_line("if [ -f x ]; then echo y; fi", &mut state);
+
+    assert_eq!(output, "if [ -f x ]; then echo y; fi");
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(state.diagnostics[0].severity, Severity::Warning);
+}
+
+#[test]
+fn convert_line_compound_while() {
+    let mut state = State::default();
+    let output = convert_line("while true; do sleep 1; done", &mut state);
+
+    assert_eq!(output, "while true; do sleep 1; done");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_compound_case() {
+    let mut state = State::default();
+    let output = convert_line("case $x in a|b) echo y;; esac", &mut state);
+
+    assert_eq!(output, "case $x in a|b) echo y;; esac");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_compound_in_list() {
+    let mut state = State::default();
+    let output = convert_line("make && for f in *.rs; do echo $f; done", &mut state);
+
+    assert_eq!(output, "make && for f in *.rs; do echo $f; done");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_echo() {
+    let output = convert_line("echo hello $USER", &mut State::default());
+
+    assert_eq!(output, "echo hello %USER%");
+}
+
+#[test]
+fn convert_line_echo_empty() {
+    let mut output = convert_line("echo", &mut State::default());
+    assert_eq!(output, "echo.");
+
+    output = convert_line("echo \"\"", &mut State::default());
+    assert_eq!(output, "echo.");
+}
+
+#[test]
+fn convert_line_echo_quoted() {
+    let output = convert_line(r#"echo "a  b" 'c&d' e\|f"#, &mut State::default());
+
+    assert_eq!(output, "echo a  b c^&d e^|f");
+}
+
+#[test]
+fn convert_line_echo_on_off() {
+    let mut output = convert_line("echo off", &mut State::default());
+    assert_eq!(output, "echo.off");
+
+    output = convert_line("echo ON", &mut State::default());
+    assert_eq!(output, "echo.ON");
+}
+
+#[test]
+fn convert_line_echo_redirection() {
+    let output = convert_line("echo \"a > b\" > out/file.txt", &mut State::default());
+
+    assert_eq!(output, ">\"out\\file.txt\" echo a ^> b");
+}
+
+#[test]
+fn convert_line_echo_redirection_no_trailing_space() {
+    let output = convert_line("echo hi > out.txt 2>&1", &mut State::default());
+
+    assert_eq!(output, r#">"out.txt" 2>&1 echo hi"#);
+}
+
+#[test]
+fn convert_line_echo_no_new_line() {
+    let output = convert_line("echo -n \"a & b\"", &mut State::default());
+
+    assert_eq!(output, "(<nul set /p =\"a & b\" & (call ))");
+}
+
+#[test]
+fn convert_line_echo_escapes() {
+    let output = convert_line(r#"echo -e "a\tb\n\nc\\d""#, &mut State::default());
+
+    assert_eq!(output, "(echo a\tb& echo.& echo c\\d)");
+}
+
+#[test]
+fn convert_line_echo_escapes_parenthesis() {
+    let output = convert_line(r#"echo -e "(a)\nb""#, &mut State::default());
+
+    assert_eq!(output, "(echo ^(a^)& echo b)");
+}
+
+#[test]
+fn convert_line_echo_escapes_stop_output() {
+    let output = convert_line(r#"echo -e "a\nb\cc""#, &mut State::default());
+
+    assert_eq!(output, "(echo a& <nul set /p =\"b\" & (call ))");
+}
+
+#[test]
+fn convert_line_echo_escapes_without_flag() {
+    let output = convert_line(r#"echo "a\tb""#, &mut State::default());
+
+    assert_eq!(output, "echo a\\tb");
+}
+
+#[test]
+fn convert_line_echo_combined_flags() {
+    let output = convert_line(r#"echo -ne "a\n" -n"#, &mut State::default());
+
+    assert_eq!(output, "(echo a& <nul set /p =\" -n\" & (call ))");
+}
+
+#[test]
+fn convert_line_printf_string() {
+    let output = convert_line(r#"printf '%s\n' "$X""#, &mut State::default());
+
+    assert_eq!(output, "echo %X%");
+}
+
+#[test]
+fn convert_line_printf_string_with_backslashes() {
+    let output = convert_line(r#"printf '%s\n' 'C:\new\table'"#, &mut State::default());
+
+    assert_eq!(output, r#"echo C:\new\table"#);
+}
+
+#[test]
+fn convert_line_printf_escaped_argument() {
+    let output = convert_line(r#"printf '%b' 'a\tb\n' 'c\c' d"#, &mut State::default());
+
+    assert_eq!(output, "(echo a\tb& <nul set /p =\"c\" & (call ))");
+}
+
+#[test]
+fn convert_line_printf_text_and_argument() {
+    let output = convert_line(
+        r#"printf "Building %s...\n" "$NAME""#,
+        &mut State::default(),
+    );
+
+    assert_eq!(output, "echo Building %NAME%...");
+}
+
+#[test]
+fn convert_line_printf_no_new_line() {
+    let output = convert_line("printf 'a & b %d'  5", &mut State::default());
+
+    assert_eq!(output, "(<nul set /p =\"a & b 5\" & (call ))");
+}
+
+#[test]
+fn convert_line_printf_multiple_lines() {
+    let output = convert_line(r#"printf "a\n\nb %s%%\n" 50"#, &mut State::default());
+
+    assert_eq!(output, "(echo a& echo.& echo b 50%%)");
+}
+
+#[test]
+fn convert_line_printf_reuse_format() {
+    let output = convert_line(r#"printf "%s=%s\n" a 1 b"#, &mut State::default());
+
+    assert_eq!(output, "(echo a=1& echo b=)");
+}
+
+#[test]
+fn convert_line_printf_missing_arguments() {
+    let output = convert_line(r#"printf "[%s]\n""#, &mut State::default());
+
+    assert_eq!(output, "echo []");
+}
+
+#[test]
+fn convert_line_printf_width() {
+    let mut state = State::default();
+    let output = convert_line(r#"printf "%-10s|%.2f\n" a 1.5 > out"#, &mut state);
+
+    assert_eq!(output, ">\"out\" echo a^|1.5");
+    assert_eq!(state.diagnostics.len(), 3);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "printf flags, width and precision in %-10s are not supported and are ignored"
+    );
+    assert_eq!(
+        state.diagnostics[2].message,
+        "printf conversion %f is not supported and the argument is printed as is"
+    );
+}
+
+#[test]
+fn convert_line_printf_no_format() {
+    let mut state = State::default();
+    let output = convert_line("printf", &mut state);
+
+    assert_eq!(output, "");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+fn words(text: &str) -> Vec<String> {
+    parser::split_words(text)
+}
+
+fn command_option(name: &str, value: Option<&str>) -> CommandOption {
+    CommandOption {
+        name: name.to_string(),
+        value: value.map(|value| value.to_string()),
+    }
+}
+
+#[test]
+fn parse_options_combined_flags() {
+    let (options, operands) = parse_options(words("-rf a --force - b"), &[]);
+
+    assert_eq!(
+        options,
+        vec![
+            command_option("r", None),
+            command_option("f", None),
+            command_option("force", None),
+        ]
+    );
+    assert_eq!(operands, vec!["a", "-", "b"]);
+}
+
+#[test]
+fn parse_options_values() {
+    let (options, operands) = parse_options(
+        words("-vtdir1 -t dir2 --target=dir3 --target dir4 --mode=755 a"),
+        &["t", "target"],
+    );
+
+    assert_eq!(
+        options,
+        vec![
+            command_option("v", None),
+            command_option("t", Some("dir1")),
+            command_option("t", Some("dir2")),
+            command_option("target", Some("dir3")),
+            command_option("target", Some("dir4")),
+            command_option("mode", Some("755")),
+        ]
+    );
+    assert_eq!(operands, vec!["a"]);
+}
+
+#[test]
+fn parse_options_end_of_options() {
+    let (options, operands) = parse_options(words("-r -- -f --force"), &[]);
+
+    assert_eq!(options, vec![command_option("r", None)]);
+    assert_eq!(operands, vec!["-f", "--force"]);
+}
+
+#[test]
+fn command_option_display() {
+    assert_eq!(command_option("r", None).to_string(), "-r");
+    assert_eq!(command_option("t", Some("dir")).to_string(), "-t");
+    assert_eq!(command_option("color", None).to_string(), "--color");
+    assert_eq!(
+        command_option("color", Some("auto")).to_string(),
+        "--color=auto"
+    );
+}
+
+#[test]
+fn convert_line_cat() {
+    let output = convert_line("cat ~/config.txt", &mut State::default());
+
+    assert_eq!(output, r#"type "%USERPROFILE%\config.txt""#);
+}
+
+#[test]
+fn convert_line_cat_multiple_files() {
+    let output = convert_line("cat a.txt dir/b.txt > all.txt", &mut State::default());
+
+    assert_eq!(output, r#"type "a.txt" "dir\b.txt" 2>nul >"all.txt""#);
+}
+
+#[test]
+fn convert_line_cat_input() {
+    let output = convert_line("cat", &mut State::default());
+
+    assert_eq!(output, r#"findstr "^""#);
+}
+
+#[test]
+fn convert_line_cat_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("cat -n file", &mut state);
+
+    assert_eq!(output, r#"type "file""#);
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "cat option -n is not supported and is ignored"
+    );
+}
+
+#[test]
+fn run_cat_heredoc() {
+    let output = run("cat > out.txt <<EOF\nhello $NAME\n  a & (b)\n\nEOF\necho done");
+
+    assert_eq!(
+        output,
+        "(echo hello %NAME%& echo   a ^& ^(b^)& echo.) >\"out.txt\"\necho done"
+    );
+}
+
+#[test]
+fn run_cat_heredoc_single_line() {
+    let output = run("cat > file <<EOF\nhello $USER\nEOF");
+
+    assert_eq!(output, r#">"file" echo hello %USER%"#);
+}
+
+#[test]
+fn run_cat_heredoc_quoted_delimiter() {
+    let output = run("cat <<-'END' >> out.txt\n\t50% of $HOME\n\tEND");
+
+    assert_eq!(output, ">>\"out.txt\" echo 50%% of $HOME");
+}
+
+#[test]
+fn run_cat_heredoc_empty() {
+    let output = run("cat > empty.txt << EOF\nEOF");
+
+    assert_eq!(output, "type nul >\"empty.txt\"");
+}
+
+#[test]
+fn run_heredoc_unsupported_command() {
+    let conversion = run_with_diagnostics("python <<EOF\nprint(1)\nEOF\necho done");
+
+    assert_eq!(conversion.script, "python <<EOF\necho done");
+    assert_eq!(conversion.diagnostics.len(), 1);
+    assert_eq!(conversion.diagnostics[0].line, 1);
+}
+
+#[test]
+fn convert_line_head() {
+    let output = convert_line("head -n 5 file.txt", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(for /f "tokens=1* delims=:" %%a in ('findstr /n "^" "file.txt"') do @if %%a leq 5 echo(%%b)"#
+    );
+}
+
+#[test]
+fn convert_line_head_input() {
+    let output = convert_line("cat log | head -3 > out", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"type "log" | (for /f "tokens=1* delims=:" %%a in ('findstr /n "^"') do @if %%a leq 3 echo(%%b) >"out""#
+    );
+}
+
+#[test]
+fn convert_line_head_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("head -c 10 file", &mut state);
+
+    assert_eq!(
+        output,
+        r#"(for /f "tokens=1* delims=:" %%a in ('findstr /n "^" "file"') do @if %%a leq 10 echo(%%b)"#
+    );
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_tail() {
+    let output = convert_line("tail --lines=3 file", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(for /f %%c in ('find /c /v "" ^< "file"') do @if %%c gtr 3 (set /a "SHELL2BATCH_SKIP=%%c-3" >nul & call more +%%SHELL2BATCH_SKIP%% "file") else type "file")"#
+    );
+}
+
+#[test]
+fn convert_line_tail_from_line() {
+    let output = convert_line("tail -n +2 data.csv", &mut State::default());
+
+    assert_eq!(output, r#"more +1 "data.csv""#);
+}
+
+#[test]
+fn convert_line_tail_input() {
+    let mut state = State::default();
+    let output = convert_line("tail -n 5", &mut state);
+
+    assert_eq!(output, "more");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_wc() {
+    let output = convert_line("wc -l file.txt", &mut State::default());
+
+    assert_eq!(output, r#"find /c /v "" < "file.txt""#);
+}
+
+#[test]
+fn convert_line_wc_input() {
+    let output = convert_line("cat file | wc -l", &mut State::default());
+
+    assert_eq!(output, r#"type "file" | find /c /v """#);
+}
+
+#[test]
+fn convert_line_wc_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("wc -w file.txt", &mut state);
+
+    assert_eq!(output, r#"find /c /v "" < "file.txt""#);
+    assert_eq!(state.diagnostics.len(), 2);
+}
+
+#[test]
+fn convert_line_clear() {
+    let output = convert_line("clear", &mut State::default());
+
+    assert_eq!(output, "cls");
+}
+
+#[test]
+fn convert_line_grep_no_pattern() {
+    let mut state = State::default();
+    let output = convert_line("grep", &mut state);
+
+    assert_eq!(output, "findstr");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_grep_literal() {
+    let output = convert_line("grep -i 'hello world' notes.txt", &mut State::default());
+
+    assert_eq!(output, r#"find /I "hello world" < "notes.txt""#);
+}
+
+#[test]
+fn convert_line_grep_literal_input() {
+    let output = convert_line("ls | grep -v \"$NAME\"", &mut State::default());
+
+    assert_eq!(output, r#"dir | find /V "%NAME%""#);
+}
+
+#[test]
+fn convert_line_grep_quiet() {
+    let output = convert_line("grep -q TODO src/main.rs", &mut State::default());
+
+    assert_eq!(output, r#"find "TODO" < "src\main.rs" >nul"#);
+}
+
+#[test]
+fn convert_line_grep_count() {
+    let output = convert_line("grep -c error log.txt", &mut State::default());
+
+    assert_eq!(output, r#"find /C "error" < "log.txt""#);
+}
+
+#[test]
+fn convert_line_grep_regex() {
+    let output = convert_line("grep -n '^fn .*(' src/lib.rs", &mut State::default());
+
+    assert_eq!(output, r#"findstr /N /R /C:"^fn .*(" "src\lib.rs""#);
+}
+
+#[test]
+fn convert_line_grep_recursive() {
+    let output = convert_line("grep -rl TODO src docs/", &mut State::default());
+
+    assert_eq!(output, r#"findstr /S /M /L /C:"TODO" "src\*" "docs\*""#);
+}
+
+#[test]
+fn convert_line_grep_recursive_current_directory() {
+    let output = convert_line("grep -ri --no-messages todo", &mut State::default());
+
+    assert_eq!(output, r#"findstr /S /I /L /C:"todo" "*" 2>nul"#);
+}
+
+#[test]
+fn convert_line_grep_multiple_files() {
+    let output = convert_line("grep main a.rs b.rs", &mut State::default());
+
+    assert_eq!(output, r#"findstr /L /C:"main" "a.rs" "b.rs""#);
+}
+
+#[test]
+fn convert_line_grep_multiple_patterns() {
+    let output = convert_line("grep -e foo -ebar --regexp=baz file", &mut State::default());
+
+    assert_eq!(output, r#"findstr /L /C:"foo" /C:"bar" /C:"baz" "file""#);
+}
+
+#[test]
+fn convert_line_grep_extended() {
+    let mut state = State::default();
+    let output = convert_line(
+        r#"grep -E '[[:digit:]]+\.\d+|v[0-9]?' versions.txt"#,
+        &mut state,
+    );
+
+    assert_eq!(
+        output,
+        r#"findstr /R /C:"[0-9][0-9]*\.[0-9][0-9]*" /C:"v[0-9]" "versions.txt""#
+    );
+    assert_eq!(state.diagnostics.len(), 1);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "grep regular expression ? is not supported by findstr and is ignored"
+    );
+}
+
+#[test]
+fn convert_line_grep_basic_operators() {
+    let output = convert_line(r#"grep 'a+b|c' file"#, &mut State::default());
+
+    assert_eq!(output, r#"find "a+b|c" < "file""#);
+}
+
+#[test]
+fn convert_line_grep_fixed_word() {
+    let output = convert_line(r#"grep -Fwx 'a.b' file"#, &mut State::default());
+
+    assert_eq!(output, r#"findstr /X /R /C:"\<a\.b\>" "file""#);
+}
+
+#[test]
+fn convert_line_grep_quoted_pattern() {
+    let output = convert_line(r#"grep -x '"name": "x"' a b"#, &mut State::default());
+
+    assert_eq!(output, r#"findstr /X /L /C:"\"name\": \"x\"" "a" "b""#);
+}
+
+#[test]
+fn convert_line_grep_unsupported_option() {
+    let mut state = State::default();
+    let output = convert_line("grep -A 3 -o error log", &mut state);
+
+    assert_eq!(output, r#"find "error" < "log""#);
+    assert_eq!(state.diagnostics.len(), 2);
+}
+
+#[test]
+fn convert_line_pwd() {
+    let output = convert_line("pwd", &mut State::default());
+
+    assert_eq!(output, "chdir");
+}
+
+#[test]
+fn convert_line_export() {
+    let output = convert_line("export A=B", &mut State::default());
+
+    assert_eq!(output, "set \"A=B\"");
+}
+
+#[test]
+fn convert_line_export_multiple() {
+    let output = convert_line("export A=1 B=$C", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\"\nset \"B=%C%\"");
+}
+
+#[test]
+fn convert_line_export_without_value() {
+    let output = convert_line("export A", &mut State::default());
+
+    assert_eq!(output, "");
+}
+
+#[test]
+fn convert_line_export_mixed() {
+    let output = convert_line("export A B=2", &mut State::default());
+
+    assert_eq!(output, "set \"B=2\"");
+}
+
+#[test]
+fn convert_line_export_value_with_spaces() {
+    let output = convert_line("export MSG=hello world", &mut State::default());
+
+    assert_eq!(output, "set \"MSG=hello\"");
+}
+
+#[test]
+fn convert_line_export_quoted_value() {
+    let output = convert_line(
+        r#"export URL="a&b" MSG='hello world'"#,
+        &mut State::default(),
+    );
+
+    assert_eq!(output, "set \"URL=a&b\"\nset \"MSG=hello world\"");
+}
+
+#[test]
+fn convert_line_export_quoted_value_with_vars() {
+    let output = convert_line(
+        r#"export A="$B c" D='$E' F=\$G H=x\ y"#,
+        &mut State::default(),
+    );
+
+    assert_eq!(
+        output,
+        "set \"A=%B% c\"\nset \"D=$E\"\nset \"F=$G\"\nset \"H=x y\""
+    );
+}
+
+#[test]
+fn convert_line_export_value_with_quotes() {
+    let output = convert_line(r#"export A='say "hi"' B="a\"b""#, &mut State::default());
+
+    assert_eq!(output, "set \"A=say \"hi\"\"\nset \"B=a\"b\"");
+}
+
+#[test]
+fn convert_line_assignment() {
+    let output = convert_line("A=1", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\"");
+}
+
+#[test]
+fn convert_line_assignment_multiple() {
+    let output = convert_line("A=1 B=", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\"\nset \"B=\"");
+}
+
+#[test]
+fn convert_line_readonly() {
+    let output = convert_line("readonly A=1", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\"");
+}
+
+#[test]
+fn convert_line_declare_with_flags() {
+    let output = convert_line("declare -x A=1", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\"");
+}
+
+#[test]
+fn convert_line_local() {
+    let output = convert_line("local A=1 B", &mut State::default());
+
+    assert_eq!(output, "set \"A=1\"");
+}
+
+#[test]
+fn convert_line_env_prefix() {
+    let output = convert_line("RUST_LOG=debug cargo test", &mut State::default());
+
+    assert_eq!(
+        output,
+        "setlocal & set \"RUST_LOG=debug\" & cargo test & endlocal"
+    );
+}
+
+#[test]
+fn convert_line_env_prefix_multiple() {
+    let output = convert_line("A=1 B=$C cp dir/file1 dir/file2", &mut State::default());
+
+    assert_eq!(
+        output,
+        "setlocal & set \"A=1\" & set \"B=%C%\" & copy \"dir\\file1\" \"dir\\file2\" & endlocal"
+    );
+}
+
+#[test]
+fn convert_line_env_command() {
+    let output = convert_line("env FOO=bar cargo build", &mut State::default());
+
+    assert_eq!(
+        output,
+        "setlocal & set \"FOO=bar\" & cargo build & endlocal"
+    );
+}
+
+#[test]
+fn convert_line_env_command_without_assignments() {
+    let output = convert_line("env cargo build", &mut State::default());
+
+    assert_eq!(output, "cargo build");
+}
+
+#[test]
+fn convert_line_env_without_command() {
+    let output = convert_line("env", &mut State::default());
+
+    assert_eq!(output, "set");
+}
+
+#[test]
+fn convert_line_env_prefix_and_list() {
+    let output = convert_line(
+        "RUST_LOG=debug cargo test && echo ok",
+        &mut State::default(),
+    );
+
+    assert_eq!(
+        output,
+        r#"cmd /c "set "RUST_LOG=debug" & cargo test" && echo ok"#
+    );
+}
+
+#[test]
+fn convert_line_env_prefix_or_list() {
+    let output = convert_line("FOO=1 cargo test || echo failed", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"cmd /c "set "FOO=1" & cargo test" || echo failed"#
+    );
+}
+
+#[test]
+fn convert_line_env_options() {
+    let mut state = State::default();
+    let output = convert_line("env -i -u HOME --unset=TMP -- FOO=1 make", &mut state);
+
+    assert_eq!(
+        output,
+        "setlocal & set HOME= & set TMP= & set \"FOO=1\" & make & endlocal"
+    );
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_env_prefix_cd() {
+    let mut state = State::default();
+    let output = convert_line("FOO=1 cd sub", &mut state);
+
+    assert_eq!(output, "cd /d \"sub\"");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_unset() {
+    let output = convert_line("unset A", &mut State::default());
+
+    assert_eq!(output, "set A=");
+}
+
+#[test]
+fn convert_line_touch() {
+    let output = convert_line("touch ./dir/myfile.txt", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"(if exist ".\dir\myfile.txt" (copy /B ".\dir\myfile.txt"+,, ".\dir\myfile.txt" >nul) else (type nul >> ".\dir\myfile.txt"))"#
+    );
+}
+
+#[test]
+fn convert_line_touch_multiple_files() {
+    let output = convert_line("touch a b", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"((if exist "a" (copy /B "a"+,, "a" >nul) else (type nul >> "a")) & (if exist "b" (copy /B "b"+,, "b" >nul) else (type nul >> "b")))"#
+    );
+}
+
+#[test]
+fn convert_line_touch_no_create() {
+    let output = convert_line("touch -cm stamp", &mut State::default());
+
+    assert_eq!(
+        output,
+        r#"if exist "stamp" copy /B "stamp"+,, "stamp" >nul"#
+    );
+}
+
+#[test]
+fn convert_line_touch_no_files() {
+    let output = convert_line("touch", &mut State::default());
+
+    assert_eq!(output, "cd .");
+}
+
+#[test]
+fn convert_line_touch_access_time() {
+    let mut state = State::default();
+    let output = convert_line("touch -a -d yesterday --no-create stamp", &mut state);
+
+    assert_eq!(
+        output,
+        r#"if exist "stamp" copy /B "stamp"+,, "stamp" >nul"#
+    );
+    assert_eq!(state.diagnostics.len(), 2);
+    assert_eq!(
+        state.diagnostics[1].message,
+        "touch -d is not supported and the current time is used"
+    );
+}
+
+#[test]
+fn convert_line_set_minus_x() {
+    let output = convert_line("set -x", &mut State::default());
+
+    assert_eq!(output, "@echo on");
+}
+
+#[test]
+fn convert_line_set_plus_x() {
+    let output = convert_line("set +x", &mut State::default());
+
+    assert_eq!(output, "@echo off");
+}
+
+#[test]
+fn convert_line_set_no_arguments() {
+    let output = convert_line("set", &mut State::default());
+
+    assert_eq!(output, "set");
+}
+
+#[test]
+fn convert_line_set_minus_e() {
+    let mut state = State::default();
+    let output = convert_line("set -e", &mut state);
+
+    assert_eq!(output, "");
+    assert!(state.errexit);
+}
+
+#[test]
+fn convert_line_set_plus_e() {
+    let mut state = State {
+        errexit: true,
+        ..State::default()
+    };
+    let output = convert_line("set +e", &mut state);
+
+    assert_eq!(output, "");
+    assert!(!state.errexit);
+}
+
+#[test]
+fn convert_line_set_combined() {
+    let mut state = State::default();
+    let output = convert_line("set -ex", &mut state);
+
+    assert_eq!(output, "@echo on");
+    assert!(state.errexit);
+}
+
+#[test]
+fn convert_line_set_multiple_clusters() {
+    let mut state = State::default();
+    let output = convert_line("set -eu -o pipefail", &mut state);
+
+    assert_eq!(output, "");
+    assert!(state.errexit);
+}
+
+#[test]
+fn convert_line_set_long_options() {
+    let mut state = State::default();
+    let output = convert_line("set -o errexit -o xtrace", &mut state);
+
+    assert_eq!(output, "@echo on");
+    assert!(state.errexit);
+    assert!(state.diagnostics.is_empty());
+}
+
+#[test]
+fn convert_line_set_long_options_disable() {
+    let mut state = State {
+        errexit: true,
+        ..State::default()
+    };
+    let output = convert_line("set +o errexit +o xtrace", &mut state);
+
+    assert_eq!(output, "@echo off");
+    assert!(!state.errexit);
+}
+
+#[test]
+fn convert_line_set_verbose() {
+    let output = convert_line("set -v", &mut State::default());
+
+    assert_eq!(output, "@echo on");
+}
+
+#[test]
+fn convert_line_set_cluster_with_long_option() {
+    let mut state = State::default();
+    let output = convert_line("set -euxo pipefail", &mut state);
+
+    assert_eq!(output, "@echo on");
+    assert!(state.errexit);
+    assert_eq!(state.diagnostics.len(), 2);
+    assert_eq!(state.diagnostics[0].severity, Severity::Warning);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "set option nounset has no batch equivalent and is ignored"
+    );
+    assert_eq!(
+        state.diagnostics[1].message,
+        "set option pipefail has no batch equivalent and is ignored"
+    );
+}
+
+#[test]
+fn convert_line_set_unsupported_options() {
+    let mut state = State::default();
+    let output = convert_line("set -f -o noclobber", &mut state);
+
+    assert_eq!(output, "");
+    assert_eq!(state.diagnostics.len(), 2);
+    assert_eq!(
+        state.diagnostics[0].message,
+        "unsupported set option -f is ignored"
+    );
+    assert_eq!(
+        state.diagnostics[1].message,
+        "unsupported set option noclobber is ignored"
+    );
+}
+
+#[test]
+fn convert_line_set_print_options() {
+    let mut state = State::default();
+    let output = convert_line("set -o", &mut state);
+
+    assert_eq!(output, "");
+    assert_eq!(state.diagnostics.len(), 1);
+}
+
+#[test]
+fn convert_line_set_positional_parameters() {
+    let mut state = State::default();
+    let output = convert_line("set -x -- a b", &mut state);
+
+    assert_eq!(output, "@echo on");
+    assert_eq!(
+        state.diagnostics[0].message,
+        "setting positional parameters is not supported and is ignored"
+    );
+}
+
+#[test]
+fn run_with_options_propagate_exit_code() {
+    let mut options = Options::new();
+    options.propagate_exit_code = true;
+    let conversion = run_with_options("cp file1 file2\nexit 3", &options);
+
+    assert_eq!(
+        conversion.script,
+        "copy \"file1\" \"file2\"\nexit /b 3\nexit /b %ERRORLEVEL%"
+    );
+}
+
+#[test]
+fn run_with_options_propagate_exit_code_empty_last_line() {
+    let mut options = Options::new();
+    options.propagate_exit_code = true;
+    let conversion = run_with_options("cp file1 file2\n", &options);
+
+    assert_eq!(
+        conversion.script,
+        "copy \"file1\" \"file2\"\nexit /b %ERRORLEVEL%"
+    );
+}
+
+#[test]
+fn run_errexit_with_exit() {
+    let output = run("set -e\nexit 1");
+
+    assert_eq!(output, "\nexit /b 1");
+}
+
+#[test]
+fn run_with_diagnostics_line_numbers() {
+    let conversion = run_with_diagnostics("set -x\n\nset -u");
+
+    assert_eq!(conversion.script, "@echo on\n\n");
+    assert_eq!(conversion.diagnostics.len(), 1);
+    assert_eq!(conversion.diagnostics[0].line, 3);
+}
+
+#[test]
+fn is_command_commands() {
+    assert!(is_command("cp a b"));
+    assert!(is_command("A=1 cargo test"));
+    assert!(is_command("test # shell2batch: windows test"));
+}
+
+#[test]
+fn is_command_not_commands() {
+    assert!(!is_command(""));
+    assert!(!is_command("#comment"));
+    assert!(!is_command("set -e"));
+    assert!(!is_command("export A=1"));
+    assert!(!is_command("unset A"));
+    assert!(!is_command("A=1 B=2"));
+    assert!(!is_command("while true; do make; done"));
+}
+
+#[test]
+fn convert_line_var_as_command() {
+    let output = convert_line("$MYVAR", &mut State::default());
+
+    assert_eq!(output, "%MYVAR%");
+}
+
+#[test]
+fn convert_line_var_as_part_of_command() {
+    let output = convert_line("./${MYVAR}.exe/something", &mut State::default());
+
+    assert_eq!(output, ".\\%MYVAR%.exe\\something");
+}
+
+#[test]
+fn convert_text_empty() {
+    let value = convert_text("", &EscapeContext::new());
+
+    assert_eq!(value, "");
+}
+
+#[test]
+fn convert_text_operators_unchanged() {
+    let value = convert_text("a && b || c | d > e 2>&1", &EscapeContext::new());
+
+    assert_eq!(value, "a && b || c | d > e 2>&1");
+}
+
+#[test]
+fn convert_text_percent() {
+    let value = convert_text(r#"50% "50%" '50%'"#, &EscapeContext::new());
+
+    assert_eq!(value, r#"50%% "50%%" "50%%""#);
+}
+
+#[test]
+fn convert_text_double_quoted() {
+    let value = convert_text(r#""a&b" "c\"&d""#, &EscapeContext::new());
+
+    assert_eq!(value, r#""a&b" "c\"&d""#);
+}
+
+#[test]
+fn convert_text_single_quoted() {
+    let value = convert_text("'a&b|c'", &EscapeContext::new());
+
+    assert_eq!(value, "\"a&b|c\"");
+}
+
+#[test]
+fn convert_text_backslash_escaped() {
+    let value = convert_text(r"a\&b \$c d\\e", &EscapeContext::new());
+
+    assert_eq!(value, r"a^&b $c d\\e");
+}
+
+#[test]
+fn convert_text_delayed_expansion() {
+    let context = EscapeContext {
+        delayed_expansion: true,
+        ..EscapeContext::new()
+    };
+    let value = convert_text(r#"a! "b!""#, &context);
+
+    assert_eq!(value, r#"a^^! "b^!""#);
+}
+
+#[test]
+fn convert_line_escape_percent() {
+    let output = convert_line("echo 50% done", &mut State::default());
+
+    assert_eq!(output, "echo 50%% done");
+}
+
+#[test]
+fn convert_line_escape_quoted_url() {
+    let output = convert_line(
+        r#"curl "http://example.com/?a=1&b=2""#,
+        &mut State::default(),
+    );
+
+    assert_eq!(output, r#"curl "http://example.com/?a=1&b=2""#);
+}
+
+#[test]
+fn convert_line_escape_assignment() {
+    let output = convert_line("export PROGRESS=50%", &mut State::default());
+
+    assert_eq!(output, "set \"PROGRESS=50%%\"");
+}
+
+#[test]
+fn run_delayed_expansion() {
+    let output = run(r#"echo hello!
+        # shell2batch: setlocal EnableDelayedExpansion
+        echo hello!"#);
+
+    assert_eq!(
+        output,
+        "echo hello!\nsetlocal EnableDelayedExpansion\necho hello^^!"
+    );
+}
+
+#[test]
+fn convert_text_single_quoted_vars() {
+    let value = convert_text("'$HOME is literal'", &EscapeContext::new());
+
+    assert_eq!(value, "\"$HOME is literal\"");
+}
+
+#[test]
+fn convert_text_double_quoted_vars() {
+    let value = convert_text(r#""$HOME ${USER}" '$HOME'"#, &EscapeContext::new());
+
+    assert_eq!(value, r#""%HOME% %USER%" "$HOME""#);
+}
+
+#[test]
+fn convert_text_escaped_dollar() {
+    let value = convert_text(r#"\$HOME "\$HOME" $HOME"#, &EscapeContext::new());
+
+    assert_eq!(value, r#"$HOME "$HOME" %HOME%"#);
+}
+
+#[test]
+fn convert_text_single_quoted_double_quote() {
+    let value = convert_text(r#"'say "hi"'"#, &EscapeContext::new());
+
+    assert_eq!(value, r#""say \"hi\"""#);
+}
+
+#[test]
+fn convert_text_adjacent_quotes() {
+    let value = convert_text(r#"'a b'"$C d"e"#, &EscapeContext::new());
+
+    assert_eq!(value, r#""a b%C% d"e"#);
+}
+
+#[test]
+fn convert_text_var_followed_by_path() {
+    let value = convert_text("$HOME/bin/$1x", &EscapeContext::new());
+
+    assert_eq!(value, "%HOME%/bin/%1x");
+}
+
+#[test]
+fn convert_line_single_quoted_vars() {
+    let output = convert_line("echo '$HOME is literal'", &mut State::default());
+
+    assert_eq!(output, "echo $HOME is literal");
+}
+
+#[test]
+fn convert_line_escaped_dollar() {
+    let output = convert_line("echo \\$HOME", &mut State::default());
+
+    assert_eq!(output, "echo $HOME");
+}
+
+#[test]
+fn convert_value_empty() {
+    let value = convert_value("", &EscapeContext::new());
+
+    assert_eq!(value, "");
+}
+
+#[test]
+fn convert_value_quoted() {
+    let value = convert_value(r#"a'b c'"d $E"\ f"#, &EscapeContext::new());
+
+    assert_eq!(value, "ab cd %E% f");
+}
+
+#[test]
+fn convert_value_escaped() {
+    let value = convert_value(r#"100%^"#, &EscapeContext::new());
+
+    assert_eq!(value, "100%%^");
 }
@@ -0,0 +1,71 @@
+//! # escape
+//!
+//! Escapes literal text so windows batch scripts do not treat it as special characters.
+//!
+
+#[cfg(test)]
+#[path = "./escape_test.rs"]
+mod escape_test;
+
+/// Describes where the escaped text is placed in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EscapeContext {
+    /// True if the text is written to a batch file (and not typed directly into cmd)
+    pub(crate) batch_file: bool,
+    /// True if the text is placed inside double quotes
+    pub(crate) quoted: bool,
+    /// True if the text is placed inside a parenthesised block
+    pub(crate) in_block: bool,
+    /// True if delayed expansion (`!VAR!`) is enabled
+    pub(crate) delayed_expansion: bool,
+}
+
+impl EscapeContext {
+    /// Creates a new context for unquoted text in a batch file.
+    pub(crate) fn new() -> EscapeContext {
+        EscapeContext {
+            batch_file: true,
+            quoted: false,
+            in_block: false,
+            delayed_expansion: false,
+        }
+    }
+
+    /// Returns a copy of this context with the provided quoted flag.
+    pub(crate) fn with_quoted(&self, quoted: bool) -> EscapeContext {
+        EscapeContext { quoted, ..*self }
+    }
+}
+
+/// Escapes the provided literal text based on the given context.
+pub(crate) fn escape(text: &str, context: &EscapeContext) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            // % is only escapable in batch files, in cmd it can not be escaped at all
+            '%' if context.batch_file => escaped.push_str("%%"),
+            // the caret is consumed once while parsing the line (outside of quotes) and once
+            // more while doing the delayed expansion
+            '!' if context.delayed_expansion => {
+                if context.quoted {
+                    escaped.push_str("^!");
+                } else {
+                    escaped.push_str("^^!");
+                }
+            }
+            '^' | '&' | '|' | '<' | '>' if !context.quoted => {
+                escaped.push('^');
+                escaped.push(character);
+            }
+            // parenthesis only have meaning when inside a block
+            '(' | ')' if !context.quoted && context.in_block => {
+                escaped.push('^');
+                escaped.push(character);
+            }
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
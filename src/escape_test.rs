@@ -0,0 +1,114 @@
+use super::*;
+
+#[test]
+fn escape_empty() {
+    let value = escape("", &EscapeContext::new());
+
+    assert_eq!(value, "");
+}
+
+#[test]
+fn escape_no_special_characters() {
+    let value = escape("test 123", &EscapeContext::new());
+
+    assert_eq!(value, "test 123");
+}
+
+#[test]
+fn escape_percent_batch_file() {
+    let value = escape("50% done", &EscapeContext::new());
+
+    assert_eq!(value, "50%% done");
+}
+
+#[test]
+fn escape_percent_quoted() {
+    let value = escape("50% done", &EscapeContext::new().with_quoted(true));
+
+    assert_eq!(value, "50%% done");
+}
+
+#[test]
+fn escape_percent_not_batch_file() {
+    let context = EscapeContext {
+        batch_file: false,
+        ..EscapeContext::new()
+    };
+    let value = escape("50% done", &context);
+
+    assert_eq!(value, "50% done");
+}
+
+#[test]
+fn escape_unquoted_special_characters() {
+    let value = escape("a&b|c<d>e^f", &EscapeContext::new());
+
+    assert_eq!(value, "a^&b^|c^<d^>e^^f");
+}
+
+#[test]
+fn escape_quoted_special_characters() {
+    let value = escape("a&b|c<d>e^f", &EscapeContext::new().with_quoted(true));
+
+    assert_eq!(value, "a&b|c<d>e^f");
+}
+
+#[test]
+fn escape_parenthesis_outside_block() {
+    let value = escape("(a)", &EscapeContext::new());
+
+    assert_eq!(value, "(a)");
+}
+
+#[test]
+fn escape_parenthesis_inside_block() {
+    let context = EscapeContext {
+        in_block: true,
+        ..EscapeContext::new()
+    };
+    let value = escape("(a)", &context);
+
+    assert_eq!(value, "^(a^)");
+}
+
+#[test]
+fn escape_parenthesis_inside_block_quoted() {
+    let context = EscapeContext {
+        in_block: true,
+        quoted: true,
+        ..EscapeContext::new()
+    };
+    let value = escape("(a)", &context);
+
+    assert_eq!(value, "(a)");
+}
+
+#[test]
+fn escape_exclamation_mark_without_delayed_expansion() {
+    let value = escape("hello!", &EscapeContext::new());
+
+    assert_eq!(value, "hello!");
+}
+
+#[test]
+fn escape_exclamation_mark_with_delayed_expansion() {
+    let context = EscapeContext {
+        delayed_expansion: true,
+        ..EscapeContext::new()
+    };
+    let value = escape("hello!", &context);
+
+    assert_eq!(value, "hello^^!");
+}
+
+#[test]
+fn escape_exclamation_mark_with_delayed_expansion_quoted() {
+    let context = EscapeContext {
+        delayed_expansion: true,
+        quoted: true,
+        ..EscapeContext::new()
+    };
+    let value = escape("hello!", &context);
+
+    assert_eq!(value, "hello^!");
+}
@@ -21,66 +21,64 @@
 //! # Examples
 //!
 //! ```
-//! fn main() {
-//!     let script = shell2batch::convert(
-//!         r#"
-//!         set -x
+//! let script = shell2batch::convert(
+//!     r#"
+//!     set -x
 //!
-//!         export FILE1=file1
-//!         export FILE2=file2
+//!     export FILE1=file1
+//!     export FILE2=file2
 //!
-//!         #this is some test code
-//!         cp ${FILE1} $FILE2
-//!         cp -r ${DIR1} $DIR2
+//!     #this is some test code
+//!     cp ${FILE1} $FILE2
+//!     cp -r ${DIR1} $DIR2
 //!
-//!         #another
-//!         mv file2 file3
+//!     #another
+//!     mv file2 file3
 //!
-//!         export MY_DIR=directory
+//!     export MY_DIR=directory
 //!
-//!         #flags are supported
-//!         rm -Rf ${MY_DIR}
+//!     #flags are supported
+//!     rm -Rf ${MY_DIR}
 //!
-//!         unset MY_DIR
+//!     unset MY_DIR
 //!
-//!         touch ./file3
+//!     touch ./file3
 //!
-//!         #provide custom windows command for specific shell command
-//!         complex_bash_command --flag1 value2 # shell2batch: complex_windows_command /flag10 windows_value
-//!         "#,
-//!     );
+//!     #provide custom windows command for specific shell command
+//!     complex_bash_command --flag1 value2 # shell2batch: complex_windows_command /flag10 windows_value
+//!     "#,
+//! );
 //!
-//!     assert_eq!(
-//!         script,
-//!         r#"
+//! assert_eq!(
+//!     script,
+//!     r#"
 //!@echo on
 //!
-//!set FILE1=file1
-//!set FILE2=file2
+//!set "FILE1=file1"
+//!set "FILE2=file2"
 //!
 //!@REM this is some test code
-//!copy %FILE1% %FILE2%
-//!xcopy /E %DIR1% %DIR2%
+//!copy "%FILE1%" "%FILE2%"
+//!xcopy /E /I /Y "%DIR1%" "%DIR2%\"
 //!
 //!@REM another
-//!move file2 file3
+//!move "file2" "file3"
 //!
-//!set MY_DIR=directory
+//!set "MY_DIR=directory"
 //!
 //!@REM flags are supported
-//!rmdir /S /Q %MY_DIR% 2>nul || cd .
+//!(if exist "%MY_DIR%\*" (rmdir /S /Q "%MY_DIR%") else if exist "%MY_DIR%" (del /F /Q "%MY_DIR%")) 2>nul || cd .
 //!
 //!set MY_DIR=
 //!
-//!copy /B .\file3+,, .\file3
+//!(if exist ".\file3" (copy /B ".\file3"+,, ".\file3" >nul) else (type nul >> ".\file3"))
 //!
 //!@REM provide custom windows command for specific shell command
 //!complex_windows_command /flag10 windows_value
 //!"#
-//!     );
+//! );
 //!
-//!     println!("Script: {}", script);
-//! }
+//! println!("Script: {}", script);
 //! ```
 //!
 //! # Contributing
@@ -99,73 +97,110 @@ mod lib_test;
 doc_comment::doctest!("../README.md");
 
 mod converter;
+mod escape;
+mod parser;
+pub mod types;
+
+use crate::types::{Conversion, Options};
 
 /// Converts the provided shell script and returns the windows batch script text.
 ///
 /// # Example
 ///
 /// ```
-/// fn main() {
-///     let script = shell2batch::convert(
-///         r#"
-///         set -x
+/// let script = shell2batch::convert(
+///     r#"
+///     set -x
 ///
-///         export FILE1=file1
-///         export FILE2=file2
+///     export FILE1=file1
+///     export FILE2=file2
 ///
-///         #this is some test code
-///         cp ${FILE1} $FILE2
-///         cp -r ${DIR1} $DIR2
+///     #this is some test code
+///     cp ${FILE1} $FILE2
+///     cp -r ${DIR1} $DIR2
 ///
-///         #another
-///         mv file2 file3
+///     #another
+///     mv file2 file3
 ///
-///         export MY_DIR=directory
+///     export MY_DIR=directory
 ///
-///         #flags are supported
-///         rm -Rf ${MY_DIR}
+///     #flags are supported
+///     rm -Rf ${MY_DIR}
 ///
-///         unset MY_DIR
+///     unset MY_DIR
 ///
-///         touch ./file3
+///     touch ./file3
 ///
-///         #provide custom windows command for specific shell command
-///         complex_bash_command --flag1 value2 # shell2batch: complex_windows_command /flag10 windows_value
-///         "#,
-///     );
+///     #provide custom windows command for specific shell command
+///     complex_bash_command --flag1 value2 # shell2batch: complex_windows_command /flag10 windows_value
+///     "#,
+/// );
 ///
-///     assert_eq!(
-///         script,
-///         r#"
+/// assert_eq!(
+///     script,
+///     r#"
 ///@echo on
 ///
-///set FILE1=file1
-///set FILE2=file2
+///set "FILE1=file1"
+///set "FILE2=file2"
 ///
 ///@REM this is some test code
-///copy %FILE1% %FILE2%
-///xcopy /E %DIR1% %DIR2%
+///copy "%FILE1%" "%FILE2%"
+///xcopy /E /I /Y "%DIR1%" "%DIR2%\"
 ///
 ///@REM another
-///move file2 file3
+///move "file2" "file3"
 ///
-///set MY_DIR=directory
+///set "MY_DIR=directory"
 ///
 ///@REM flags are supported
-///rmdir /S /Q %MY_DIR% 2>nul || cd .
+///(if exist "%MY_DIR%\*" (rmdir /S /Q "%MY_DIR%") else if exist "%MY_DIR%" (del /F /Q "%MY_DIR%")) 2>nul || cd .
 ///
 ///set MY_DIR=
 ///
-///copy /B .\file3+,, .\file3
+///(if exist ".\file3" (copy /B ".\file3"+,, ".\file3" >nul) else (type nul >> ".\file3"))
 ///
 ///@REM provide custom windows command for specific shell command
 ///complex_windows_command /flag10 windows_value
 ///"#
-///     );
+/// );
 ///
-///     println!("Script: {}", script);
-/// }
+/// println!("Script: {}", script);
 /// ```
 pub fn convert(script: &str) -> String {
     converter::run(script)
 }
+
+/// Converts the provided shell script and returns the windows batch script text
+/// together with diagnostics about shell constructs which could not be fully converted.
+///
+/// # Example
+///
+/// ```
+/// let conversion = shell2batch::convert_with_diagnostics("set -euo pipefail");
+///
+/// assert_eq!(conversion.script, "");
+/// for diagnostic in conversion.diagnostics {
+///     println!("{}", diagnostic);
+/// }
+/// ```
+pub fn convert_with_diagnostics(script: &str) -> Conversion {
+    converter::run_with_diagnostics(script)
+}
+
+/// Converts the provided shell script based on the provided options and returns the windows
+/// batch script text together with diagnostics about shell constructs which could not be fully converted.
+///
+/// # Example
+///
+/// ```
+/// let mut options = shell2batch::types::Options::new();
+/// options.propagate_exit_code = true;
+///
+/// let conversion = shell2batch::convert_with_options("cargo test", &options);
+///
+/// assert_eq!(conversion.script, "cargo test\nexit /b %ERRORLEVEL%");
+/// ```
+pub fn convert_with_options(script: &str, options: &Options) -> Conversion {
+    converter::run_with_options(script, options)
+}
@@ -19,7 +19,7 @@ fn convert_comment() {
 fn convert_command() {
     let output = convert("cp file1 file2");
 
-    assert_eq!(output, "copy file1 file2".to_string());
+    assert_eq!(output, "copy \"file1\" \"file2\"".to_string());
 }
 
 #[test]
@@ -41,13 +41,60 @@ fn convert_multi_line() {
         output,
         r#"
 @REM this is some test code
-copy file1 file2
+copy "file1" "file2"
 
 @REM another
-move file2 file3
+move "file2" "file3"
 
 @REM flags are supported
-rmdir /S /Q .\directory 2>nul || cd .
+(if exist ".\directory\*" (rmdir /S /Q ".\directory") else if exist ".\directory" (del /F /Q ".\directory")) 2>nul || cd .
 "#
     );
 }
+
+#[test]
+fn convert_with_diagnostics_no_diagnostics() {
+    let conversion = convert_with_diagnostics("cp file1 file2");
+
+    assert_eq!(conversion.script, "copy \"file1\" \"file2\"");
+    assert!(conversion.diagnostics.is_empty());
+}
+
+#[test]
+fn convert_with_diagnostics_set_options() {
+    let conversion = convert_with_diagnostics("\nset -euo pipefail");
+
+    assert_eq!(conversion.script, "\n");
+    assert_eq!(
+        conversion.diagnostics,
+        vec![
+            types::Diagnostic {
+                line: 2,
+                severity: types::Severity::Warning,
+                message: "set option nounset has no batch equivalent and is ignored".to_string(),
+            },
+            types::Diagnostic {
+                line: 2,
+                severity: types::Severity::Warning,
+                message: "set option pipefail has no batch equivalent and is ignored".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn convert_with_options_default() {
+    let conversion = convert_with_options("exit 1", &Options::new());
+
+    assert_eq!(conversion.script, "exit /b 1");
+}
+
+#[test]
+fn convert_with_options_propagate_exit_code() {
+    let mut options = Options::new();
+    options.propagate_exit_code = true;
+
+    let conversion = convert_with_options("cargo build\n", &options);
+
+    assert_eq!(conversion.script, "cargo build\nexit /b %ERRORLEVEL%");
+}
@@ -0,0 +1,327 @@
+//! # parser
+//!
+//! Splits shell command lines into words while keeping the shell quoting intact.
+//!
+
+#[cfg(test)]
+#[path = "./parser_test.rs"]
+mod parser_test;
+
+/// Splits the provided text into shell words.
+///
+/// Words are separated by unquoted whitespace. Quotes and backslash escapes are
+/// kept as part of the word so that later conversion steps can decide how to handle them.
+pub(crate) fn split_words(text: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for character in text.chars() {
+        if escaped {
+            word.push(character);
+            escaped = false;
+            continue;
+        }
+
+        match quote {
+            Some(quote_char) => {
+                if character == '\\' && quote_char == '"' {
+                    escaped = true;
+                } else if character == quote_char {
+                    quote = None;
+                }
+                word.push(character);
+            }
+            None => {
+                if character.is_whitespace() {
+                    if in_word {
+                        words.push(word);
+                        word = String::new();
+                        in_word = false;
+                    }
+                } else {
+                    if character == '\\' {
+                        escaped = true;
+                    } else if character == '\'' || character == '"' {
+                        quote = Some(character);
+                    }
+                    word.push(character);
+                    in_word = true;
+                }
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Returns true if the provided text is a valid shell variable name.
+pub(crate) fn is_var_name(name: &str) -> bool {
+    let mut characters = name.chars();
+
+    match characters.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Splits a `NAME=value` shell word into its name and value.
+///
+/// Returns None if the word is not an assignment.
+pub(crate) fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    let index = word.find('=')?;
+    let (name, value) = word.split_at(index);
+
+    if is_var_name(name) {
+        Some((name, &value[1..]))
+    } else {
+        None
+    }
+}
+
+/// Splits a redirection shell word (for example: `2>/dev/null`) into its operator and target.
+///
+/// The target is empty if it is provided as a separate word (for example: `> file`).<br>
+/// Returns None if the word is not a redirection.
+pub(crate) fn split_redirection(word: &str) -> Option<(&str, &str)> {
+    let descriptor_length = if word.starts_with('&') {
+        1
+    } else {
+        word.find(|character: char| !character.is_ascii_digit())
+            .unwrap_or(word.len())
+    };
+    let rest = &word[descriptor_length..];
+
+    let operator_length = if rest.starts_with("<<<") || rest.starts_with("<<-") {
+        if descriptor_length == 0 {
+            3
+        } else {
+            return None;
+        }
+    } else if rest.starts_with(">>")
+        || rest.starts_with(">&")
+        || (rest.starts_with("<<") && descriptor_length == 0)
+    {
+        2
+    } else if rest.starts_with('>') || (rest.starts_with('<') && descriptor_length == 0) {
+        1
+    } else {
+        return None;
+    };
+
+    Some(word.split_at(descriptor_length + operator_length))
+}
+
+/// The start of a here document (for example: `cat <<'EOF'`).
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct HeredocStart {
+    /// The line which ends the here document
+    pub(crate) delimiter: String,
+    /// True if variables are replaced in the here document (the delimiter is not quoted)
+    pub(crate) expand: bool,
+    /// True if leading tabs are removed from the here document lines (`<<-`)
+    pub(crate) strip_tabs: bool,
+}
+
+/// Finds the here document redirection (`<<` or `<<-`) in the provided line.
+///
+/// Returns None if the line does not start a here document.
+pub(crate) fn find_heredoc(line: &str) -> Option<HeredocStart> {
+    let words = split_words(line);
+    let mut words = words.iter();
+
+    while let Some(word) = words.next() {
+        let (operator, target) = match split_redirection(word) {
+            Some((operator, target)) if operator == "<<" || operator == "<<-" => (operator, target),
+            _ => continue,
+        };
+        let target = if target.is_empty() {
+            words.next()?.as_str()
+        } else {
+            target
+        };
+
+        return Some(HeredocStart {
+            delimiter: target.replace(['\'', '"', '\\'], ""),
+            expand: !target.contains(['\'', '"', '\\']),
+            strip_tabs: operator == "<<-",
+        });
+    }
+
+    None
+}
+
+fn is_word_boundary(character: Option<&char>) -> bool {
+    match character {
+        None => true,
+        Some(character) => character.is_whitespace() || ";&|<>".contains(*character),
+    }
+}
+
+/// Splits a command list into its commands and the operators (`&&`, `||`, `|`, `;` and `&`) which follow them.
+///
+/// Operators inside quotes, subshells (`( ... )`), command substitutions and brace groups are not split.<br>
+/// The operator of the last command is empty unless the line ends with an operator.
+pub(crate) fn split_list(line: &str) -> Vec<(String, String)> {
+    let characters: Vec<char> = line.chars().collect();
+    let mut items = vec![];
+    let mut command = String::new();
+    let mut quote: Option<char> = None;
+    let mut paren_depth = 0;
+    let mut brace_depth = 0;
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let previous_character = if index > 0 {
+            characters.get(index - 1)
+        } else {
+            None
+        };
+        let next_character = characters.get(index + 1);
+        index += 1;
+
+        if let Some(quote_char) = quote {
+            command.push(character);
+            if character == '\\' && quote_char == '"' {
+                if let Some(next_character) = next_character {
+                    command.push(*next_character);
+                    index += 1;
+                }
+            } else if character == quote_char {
+                quote = None;
+            }
+            continue;
+        }
+
+        match character {
+            '\\' => {
+                command.push(character);
+                if let Some(next_character) = next_character {
+                    command.push(*next_character);
+                    index += 1;
+                }
+            }
+            '\'' | '"' => {
+                quote = Some(character);
+                command.push(character);
+            }
+            '(' => {
+                paren_depth += 1;
+                command.push(character);
+            }
+            ')' => {
+                if paren_depth > 0 {
+                    paren_depth -= 1;
+                }
+                command.push(character);
+            }
+            // braces are only reserved words when they are separate words
+            '{' if is_word_boundary(previous_character) && is_word_boundary(next_character) => {
+                brace_depth += 1;
+                command.push(character);
+            }
+            '}' if brace_depth > 0
+                && is_word_boundary(previous_character)
+                && is_word_boundary(next_character) =>
+            {
+                brace_depth -= 1;
+                command.push(character);
+            }
+            '&' | '|' | ';' if paren_depth == 0 && brace_depth == 0 => {
+                // ampersands which are part of redirections, for example: 2>&1 or &>file
+                let redirection = character == '&'
+                    && (previous_character == Some(&'>')
+                        || previous_character == Some(&'<')
+                        || next_character == Some(&'>'));
+
+                if redirection {
+                    command.push(character);
+                } else {
+                    let mut operator = character.to_string();
+                    if character != ';' && next_character == Some(&character) {
+                        operator.push(character);
+                        index += 1;
+                    }
+
+                    items.push((command.trim().to_string(), operator));
+                    command.clear();
+                }
+            }
+            _ => command.push(character),
+        }
+    }
+
+    if !command.trim().is_empty() || items.is_empty() {
+        items.push((command.trim().to_string(), "".to_string()));
+    }
+
+    items
+}
+
+/// Splits a subshell (`( ... )`) or a brace group (`{ ...; }`) command into the group type
+/// (the opening character), the commands inside the group and the text following the group.
+///
+/// Returns None if the command is not a group.
+pub(crate) fn split_group(command: &str) -> Option<(char, String, String)> {
+    let characters: Vec<char> = command.chars().collect();
+    let group_type = match characters.first() {
+        Some('(') => '(',
+        Some('{') if is_word_boundary(characters.get(1)) => '{',
+        _ => return None,
+    };
+
+    let mut quote: Option<char> = None;
+    let mut depth = 1;
+    let mut index = 1;
+    while index < characters.len() {
+        let character = characters[index];
+        let previous_character = characters.get(index - 1);
+        let next_character = characters.get(index + 1);
+
+        match quote {
+            Some(quote_char) => {
+                if character == '\\' && quote_char == '"' {
+                    index += 1;
+                } else if character == quote_char {
+                    quote = None;
+                }
+            }
+            None => match character {
+                '\\' => index += 1,
+                '\'' | '"' => quote = Some(character),
+                '(' if group_type == '(' => depth += 1,
+                ')' if group_type == '(' => depth -= 1,
+                '{' if is_word_boundary(previous_character) && is_word_boundary(next_character) => {
+                    depth += 1
+                }
+                '}' if is_word_boundary(previous_character) && is_word_boundary(next_character) => {
+                    depth -= 1
+                }
+                _ => (),
+            },
+        }
+
+        if depth == 0 {
+            let inner: String = characters[1..index].iter().collect();
+            let rest: String = characters[index + 1..].iter().collect();
+
+            // the last command in a brace group must end with ; which is not needed after conversion
+            let inner = inner.trim().trim_end_matches(';').trim().to_string();
+
+            return Some((group_type, inner, rest.trim().to_string()));
+        }
+
+        index += 1;
+    }
+
+    None
+}
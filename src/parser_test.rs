@@ -0,0 +1,219 @@
+use super::*;
+
+#[test]
+fn split_words_empty() {
+    let words = split_words("");
+
+    assert!(words.is_empty());
+}
+
+#[test]
+fn split_words_whitespace_only() {
+    let words = split_words("   \t ");
+
+    assert!(words.is_empty());
+}
+
+#[test]
+fn split_words_simple() {
+    let words = split_words("cp  file1 \tfile2 ");
+
+    assert_eq!(words, vec!["cp", "file1", "file2"]);
+}
+
+#[test]
+fn split_words_quoted() {
+    let words = split_words(r#"echo "a b" 'c d' e"f g"h"#);
+
+    assert_eq!(words, vec!["echo", "\"a b\"", "'c d'", "e\"f g\"h"]);
+}
+
+#[test]
+fn split_words_escaped() {
+    let words = split_words(r#"echo a\ b "c\" d" 'e\'"#);
+
+    assert_eq!(words, vec!["echo", "a\\ b", "\"c\\\" d\"", "'e\\'"]);
+}
+
+#[test]
+fn is_var_name_valid() {
+    assert!(is_var_name("A"));
+    assert!(is_var_name("_a1"));
+    assert!(is_var_name("MY_VAR"));
+}
+
+#[test]
+fn is_var_name_invalid() {
+    assert!(!is_var_name(""));
+    assert!(!is_var_name("1A"));
+    assert!(!is_var_name("A-B"));
+    assert!(!is_var_name("$A"));
+}
+
+#[test]
+fn split_assignment_valid() {
+    assert_eq!(split_assignment("A=1"), Some(("A", "1")));
+    assert_eq!(split_assignment("A="), Some(("A", "")));
+    assert_eq!(split_assignment("A=b=c"), Some(("A", "b=c")));
+}
+
+#[test]
+fn split_assignment_invalid() {
+    assert_eq!(split_assignment("A"), None);
+    assert_eq!(split_assignment("=1"), None);
+    assert_eq!(split_assignment("--flag=1"), None);
+}
+
+#[test]
+fn split_redirection_valid() {
+    assert_eq!(split_redirection(">file"), Some((">", "file")));
+    assert_eq!(split_redirection(">>file"), Some((">>", "file")));
+    assert_eq!(split_redirection("2>/dev/null"), Some(("2>", "/dev/null")));
+    assert_eq!(split_redirection("2>&1"), Some(("2>&", "1")));
+    assert_eq!(split_redirection("&>log"), Some(("&>", "log")));
+    assert_eq!(split_redirection("<input"), Some(("<", "input")));
+    assert_eq!(split_redirection(">"), Some((">", "")));
+    assert_eq!(split_redirection("<<EOF"), Some(("<<", "EOF")));
+    assert_eq!(split_redirection("<<-EOF"), Some(("<<-", "EOF")));
+    assert_eq!(split_redirection("<<<text"), Some(("<<<", "text")));
+}
+
+#[test]
+fn split_redirection_invalid() {
+    assert_eq!(split_redirection("file"), None);
+    assert_eq!(split_redirection("-f"), None);
+    assert_eq!(split_redirection("2"), None);
+    assert_eq!(split_redirection("&"), None);
+    assert_eq!(split_redirection("a>b"), None);
+    assert_eq!(split_redirection("2<<EOF"), None);
+}
+
+#[test]
+fn find_heredoc_none() {
+    assert_eq!(find_heredoc("cat file > out"), None);
+    assert_eq!(find_heredoc("echo '<<EOF'"), None);
+}
+
+#[test]
+fn find_heredoc_unquoted() {
+    let heredoc = find_heredoc("cat > out <<EOF");
+
+    assert_eq!(
+        heredoc,
+        Some(HeredocStart {
+            delimiter: "EOF".to_string(),
+            expand: true,
+            strip_tabs: false,
+        })
+    );
+}
+
+#[test]
+fn find_heredoc_quoted_strip_tabs() {
+    let heredoc = find_heredoc("cat <<- 'END' > out");
+
+    assert_eq!(
+        heredoc,
+        Some(HeredocStart {
+            delimiter: "END".to_string(),
+            expand: false,
+            strip_tabs: true,
+        })
+    );
+}
+
+fn list_items(items: &[(&str, &str)]) -> Vec<(String, String)> {
+    items
+        .iter()
+        .map(|(command, operator)| (command.to_string(), operator.to_string()))
+        .collect()
+}
+
+#[test]
+fn split_list_empty() {
+    let items = split_list("");
+
+    assert_eq!(items, list_items(&[("", "")]));
+}
+
+#[test]
+fn split_list_single_command() {
+    let items = split_list("cp a b 2>&1 &>/dev/null");
+
+    assert_eq!(items, list_items(&[("cp a b 2>&1 &>/dev/null", "")]));
+}
+
+#[test]
+fn split_list_operators() {
+    let items = split_list("a && b || c | d; e & f;");
+
+    assert_eq!(
+        items,
+        list_items(&[
+            ("a", "&&"),
+            ("b", "||"),
+            ("c", "|"),
+            ("d", ";"),
+            ("e", "&"),
+            ("f", ";"),
+        ])
+    );
+}
+
+#[test]
+fn split_list_quoted_operators() {
+    let items = split_list(r#"echo "a && b" 'c; d' e\;f"#);
+
+    assert_eq!(items, list_items(&[(r#"echo "a && b" 'c; d' e\;f"#, "")]));
+}
+
+#[test]
+fn split_list_groups() {
+    let items = split_list("(cd sub && make) && { a; b; } > out; echo $(x; y) ${A}");
+
+    assert_eq!(
+        items,
+        list_items(&[
+            ("(cd sub && make)", "&&"),
+            ("{ a; b; } > out", ";"),
+            ("echo $(x; y) ${A}", ""),
+        ])
+    );
+}
+
+#[test]
+fn split_group_not_group() {
+    assert_eq!(split_group("cp a b"), None);
+    assert_eq!(split_group("{a}"), None);
+    assert_eq!(split_group("(a"), None);
+}
+
+#[test]
+fn split_group_subshell() {
+    let group = split_group("(cd sub && (make)) > out");
+
+    assert_eq!(
+        group,
+        Some(('(', "cd sub && (make)".to_string(), "> out".to_string()))
+    );
+}
+
+#[test]
+fn split_group_subshell_quoted() {
+    let group = split_group(r#"(echo ")" ')')"#);
+
+    assert_eq!(
+        group,
+        Some(('(', r#"echo ")" ')'"#.to_string(), "".to_string()))
+    );
+}
+
+#[test]
+fn split_group_brace_group() {
+    let group = split_group("{ a; { b; }; } 2>/dev/null");
+
+    assert_eq!(
+        group,
+        Some(('{', "a; { b; }".to_string(), "2>/dev/null".to_string()))
+    );
+}
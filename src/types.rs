@@ -0,0 +1,77 @@
+//! # types
+//!
+//! Defines the various types and aliases used by the library.
+//!
+
+#[cfg(test)]
+#[path = "./types_test.rs"]
+mod types_test;
+
+use std::fmt;
+
+/// The severity of a conversion diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The shell construct was converted (or dropped) and no action is needed
+    Info,
+    /// The shell construct was only partially converted and the output should be reviewed
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(formatter, "info"),
+            Severity::Warning => write!(formatter, "warning"),
+        }
+    }
+}
+
+/// Describes a shell construct which could not be fully converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The line number (starting from 1) in the shell script
+    pub line: usize,
+    /// The diagnostic severity
+    pub severity: Severity,
+    /// The diagnostic message
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "line {}: {}: {}",
+            self.line, self.severity, self.message
+        )
+    }
+}
+
+/// Holds the conversion output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The windows batch script text
+    pub script: String,
+    /// All diagnostics found while converting the script
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Options which control the conversion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Appends a final `exit /b %ERRORLEVEL%` so the batch script exit code matches
+    /// the exit code of the last command, like a shell script does (default false)
+    pub propagate_exit_code: bool,
+    /// Converts recursive copies (`cp -r`) and directory moves (`mv`) to robocopy instead of
+    /// xcopy and move (default false).<br>
+    /// rsync is always converted to robocopy.
+    pub use_robocopy: bool,
+}
+
+impl Options {
+    /// Returns new instance with the default values
+    pub fn new() -> Options {
+        Default::default()
+    }
+}
@@ -0,0 +1,26 @@
+use super::*;
+
+#[test]
+fn severity_display() {
+    assert_eq!(Severity::Info.to_string(), "info");
+    assert_eq!(Severity::Warning.to_string(), "warning");
+}
+
+#[test]
+fn diagnostic_display() {
+    let diagnostic = Diagnostic {
+        line: 3,
+        severity: Severity::Warning,
+        message: "test message".to_string(),
+    };
+
+    assert_eq!(diagnostic.to_string(), "line 3: warning: test message");
+}
+
+#[test]
+fn options_new() {
+    let options = Options::new();
+
+    assert!(!options.propagate_exit_code);
+    assert!(!options.use_robocopy);
+}